@@ -0,0 +1,147 @@
+//! Submits tickets concurrently to a running server and reports throughput and latency.
+//!
+//!     cargo run --release --example load_test -- [--url http://127.0.0.1:8080] \
+//!         [--requests 1000] [--concurrency 50]
+//!
+//! Every request creates a real ticket and queues its confirmation email, point it at a
+//! throwaway database with `MAIL_BACKEND=memory`.
+
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+
+const USAGE: &str = "Usage: load_test [--url <base url>] [--requests <n>] [--concurrency <n>]";
+
+struct Options {
+    url: String,
+    requests: usize,
+    concurrency: usize,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        url: "http://127.0.0.1:8080".to_string(),
+        requests: 1000,
+        concurrency: 50,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(USAGE)?;
+        match arg.as_str() {
+            "--url" => options.url = value.trim_end_matches('/').to_string(),
+            "--requests" => options.requests = value.parse().map_err(|_| USAGE)?,
+            "--concurrency" => options.concurrency = value.parse().map_err(|_| USAGE)?,
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    if options.requests == 0 || options.concurrency == 0 {
+        return Err(USAGE.to_string());
+    }
+    Ok(options)
+}
+
+/// Outcome of one submission
+struct Sample {
+    status: Option<u16>,
+    latency: Duration,
+}
+
+/// Sends the submissions numbered `worker`, `worker + concurrency`, ... one after the other.
+async fn run_worker(
+    client: reqwest::Client,
+    url: String,
+    worker: usize,
+    requests: usize,
+    concurrency: usize,
+) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    for n in (worker..requests).step_by(concurrency) {
+        let body = serde_json::json!({
+            "name": format!("Load test {}", n),
+            "email": format!("loadtest+{}@example.com", n),
+            "message": "Submitted by the load test",
+        });
+        let start = Instant::now();
+        let status = client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .ok()
+            .map(|res| res.status().as_u16());
+        samples.push(Sample {
+            status,
+            latency: start.elapsed(),
+        });
+    }
+    samples
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let index = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[index]
+}
+
+#[actix_web::main]
+async fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(usage) => {
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+    };
+    // Cheap to clone, every clone shares the same connection pool
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/tickets", options.url);
+    println!(
+        "Submitting {} tickets to {} with {} concurrent clients",
+        options.requests, url, options.concurrency
+    );
+
+    let start = Instant::now();
+    let workers = (0..options.concurrency.min(options.requests)).map(|worker| {
+        actix_web::rt::spawn(run_worker(
+            client.clone(),
+            url.clone(),
+            worker,
+            options.requests,
+            options.concurrency,
+        ))
+    });
+    let samples: Vec<Sample> = join_all(workers)
+        .await
+        .into_iter()
+        .filter_map(Result::ok)
+        .flatten()
+        .collect();
+    let elapsed = start.elapsed();
+
+    let created = samples.iter().filter(|s| s.status == Some(201)).count();
+    let unavailable = samples.iter().filter(|s| s.status == Some(503)).count();
+    let failed = samples.iter().filter(|s| s.status.is_none()).count();
+    let mut latencies: Vec<Duration> = samples.iter().map(|s| s.latency).collect();
+    latencies.sort();
+
+    println!("Finished in {:.2?}", elapsed);
+    println!(
+        "  {:.1} requests/s",
+        samples.len() as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "  {} created, {} unavailable (503), {} other statuses, {} connection errors",
+        created,
+        unavailable,
+        samples.len() - created - unavailable - failed,
+        failed
+    );
+    if !latencies.is_empty() {
+        println!(
+            "  latency p50 {:.2?}, p95 {:.2?}, p99 {:.2?}, max {:.2?}",
+            percentile(&latencies, 0.50),
+            percentile(&latencies, 0.95),
+            percentile(&latencies, 0.99),
+            latencies[latencies.len() - 1]
+        );
+    }
+}
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS tickets (
+    uuid TEXT PRIMARY KEY,
+    number INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    email TEXT NOT NULL,
+    message TEXT NOT NULL,
+    note TEXT,
+    status TEXT NOT NULL CHECK (status IN ('open', 'closed', 'pending')),
+    created_at TEXT NOT NULL,
+    updated_at TEXT,
+    closed_at TEXT
+);
@@ -0,0 +1 @@
+ALTER TABLE tickets ADD COLUMN reopened_count INTEGER NOT NULL DEFAULT 0;
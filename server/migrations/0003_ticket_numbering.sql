@@ -0,0 +1,29 @@
+CREATE TABLE ticket_sequence (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    value INTEGER NOT NULL
+);
+INSERT INTO ticket_sequence (id, value) SELECT 1, COALESCE(MAX(number), 0) FROM tickets;
+
+-- Concurrent submissions could be given the same number. The first ticket keeps it,
+-- the others are numbered after every existing ticket.
+CREATE TEMP TABLE ticket_renumbering AS
+SELECT
+    tickets.rowid AS ticket_rowid,
+    (SELECT value FROM ticket_sequence WHERE id = 1)
+        + ROW_NUMBER() OVER (ORDER BY tickets.created_at, tickets.rowid) AS number
+FROM tickets
+WHERE EXISTS (
+    SELECT 1 FROM tickets AS earlier
+    WHERE earlier.number = tickets.number AND earlier.rowid < tickets.rowid
+);
+UPDATE tickets
+SET number = (SELECT number FROM ticket_renumbering WHERE ticket_rowid = tickets.rowid)
+WHERE rowid IN (SELECT ticket_rowid FROM ticket_renumbering);
+UPDATE ticket_sequence SET value = value + (SELECT COUNT(*) FROM ticket_renumbering) WHERE id = 1;
+DROP TABLE ticket_renumbering;
+
+ALTER TABLE tickets ADD COLUMN reference TEXT;
+UPDATE tickets SET reference = CAST(number AS TEXT);
+
+CREATE UNIQUE INDEX idx_tickets_number ON tickets (number);
+CREATE UNIQUE INDEX idx_tickets_reference ON tickets (reference);
@@ -0,0 +1,16 @@
+CREATE TABLE ticket_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_uuid TEXT NOT NULL REFERENCES tickets (uuid) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('requester_reply', 'staff_reply', 'internal_note')),
+    author_name TEXT NOT NULL,
+    author_email TEXT,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_ticket_messages_ticket ON ticket_messages (ticket_uuid, created_at, id);
+
+-- Keep the notes written so far as the first internal note of their ticket
+INSERT INTO ticket_messages (ticket_uuid, kind, author_name, body, created_at)
+SELECT uuid, 'internal_note', 'Staff', note, COALESCE(updated_at, created_at)
+FROM tickets
+WHERE note IS NOT NULL AND note != '';
@@ -0,0 +1,11 @@
+-- No foreign key on purpose: the history of a deleted ticket is kept
+CREATE TABLE ticket_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_uuid TEXT NOT NULL,
+    field TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    actor TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_ticket_events_ticket ON ticket_events (ticket_uuid, id);
@@ -0,0 +1,45 @@
+-- Search index over tickets and their conversation, rowid mirrors tickets.number
+CREATE VIRTUAL TABLE tickets_fts USING fts5(
+    reference,
+    name,
+    email,
+    message,
+    conversation,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO tickets_fts (rowid, reference, name, email, message, conversation)
+SELECT t.number, t.reference, t.name, t.email, t.message,
+    (SELECT group_concat(m.body, ' ') FROM ticket_messages m WHERE m.ticket_uuid = t.uuid)
+FROM tickets t;
+
+CREATE TRIGGER tickets_fts_insert AFTER INSERT ON tickets BEGIN
+    INSERT INTO tickets_fts (rowid, reference, name, email, message)
+    VALUES (new.number, new.reference, new.name, new.email, new.message);
+END;
+
+CREATE TRIGGER tickets_fts_update AFTER UPDATE OF reference, name, email, message ON tickets BEGIN
+    UPDATE tickets_fts
+    SET reference = new.reference, name = new.name, email = new.email, message = new.message
+    WHERE rowid = new.number;
+END;
+
+CREATE TRIGGER tickets_fts_delete AFTER DELETE ON tickets BEGIN
+    DELETE FROM tickets_fts WHERE rowid = old.number;
+END;
+
+CREATE TRIGGER ticket_messages_fts_insert AFTER INSERT ON ticket_messages BEGIN
+    UPDATE tickets_fts
+    SET conversation = (
+        SELECT group_concat(body, ' ') FROM ticket_messages WHERE ticket_uuid = new.ticket_uuid
+    )
+    WHERE rowid = (SELECT number FROM tickets WHERE uuid = new.ticket_uuid);
+END;
+
+CREATE TRIGGER ticket_messages_fts_delete AFTER DELETE ON ticket_messages BEGIN
+    UPDATE tickets_fts
+    SET conversation = (
+        SELECT group_concat(body, ' ') FROM ticket_messages WHERE ticket_uuid = old.ticket_uuid
+    )
+    WHERE rowid = (SELECT number FROM tickets WHERE uuid = old.ticket_uuid);
+END;
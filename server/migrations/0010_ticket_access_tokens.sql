@@ -0,0 +1,9 @@
+-- Secret links given to requesters, a ticket has one per email sent until they expire or are rotated
+CREATE TABLE ticket_access_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticket_uuid TEXT NOT NULL REFERENCES tickets (uuid) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+CREATE INDEX idx_ticket_access_tokens_ticket ON ticket_access_tokens (ticket_uuid);
@@ -0,0 +1,15 @@
+-- One-time links emailed by the requester portal, each one is exchanged for a session
+CREATE TABLE portal_logins (
+    token_hash TEXT PRIMARY KEY,
+    email TEXT NOT NULL COLLATE NOCASE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+CREATE INDEX idx_portal_logins_email ON portal_logins (email);
+
+CREATE TABLE portal_sessions (
+    token_hash TEXT PRIMARY KEY,
+    email TEXT NOT NULL COLLATE NOCASE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Emails carry working ticket and portal links, only keep their bodies until they are sent
+UPDATE email_outbox SET html = '', text = NULL WHERE status = 'sent';
@@ -0,0 +1,51 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::models::Scope;
+use super::service::{self, CreateApiKeyRequest};
+use crate::users::models::User;
+use crate::utils::db;
+use crate::Pool;
+
+pub async fn get_api_keys(db: web::Data<Pool>) -> impl Responder {
+    match db::run(&db, move |conn| service::get_api_keys(conn)).await {
+        Ok(api_keys) => HttpResponse::Ok().json(api_keys),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostApiKey {
+    name: String,
+    scopes: Vec<Scope>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+pub async fn post_api_key(
+    db: web::Data<Pool>,
+    user: User,
+    body: web::Json<PostApiKey>,
+) -> impl Responder {
+    let body = body.into_inner();
+
+    let req = CreateApiKeyRequest {
+        name: body.name,
+        scopes: body.scopes,
+        expires_at: body.expires_at,
+    };
+
+    match db::run(&db, move |conn| service::create_api_key(conn, req, &user)).await {
+        Ok(created) => HttpResponse::Created().json(created),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn delete_api_key(db: web::Data<Pool>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+
+    match db::run(&db, move |conn| service::revoke_api_key(conn, id)).await {
+        Ok(api_key) => HttpResponse::Ok().json(api_key),
+        Err(e) => e.error_response(),
+    }
+}
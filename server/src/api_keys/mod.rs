@@ -0,0 +1,5 @@
+pub mod handlers;
+pub mod models;
+mod repository;
+pub mod routes;
+pub(crate) mod service;
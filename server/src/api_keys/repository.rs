@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use super::models::{ApiKey, Scope};
+
+fn scopes_to_sql(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(Scope::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn api_key_from_row(row: &rusqlite::Row) -> Result<ApiKey, rusqlite::Error> {
+    let scopes: String = row.get("scopes")?;
+    Ok(ApiKey {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        prefix: row.get("prefix")?,
+        scopes: scopes
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, String>>()
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+            })?,
+        created_by: row.get("created_by")?,
+        created_at: row.get("created_at")?,
+        expires_at: row.get("expires_at")?,
+        last_used_at: row.get("last_used_at")?,
+        revoked_at: row.get("revoked_at")?,
+    })
+}
+
+pub fn get_all(conn: &Connection) -> Result<Vec<ApiKey>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM api_keys ORDER BY id;")?;
+    stmt.query_map([], api_key_from_row)
+        .and_then(Iterator::collect)
+}
+
+pub fn get_by_id(conn: &Connection, id: i64) -> Result<ApiKey, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM api_keys WHERE id = ?1;")?;
+    stmt.query_row([id], api_key_from_row)
+}
+
+/// A key that is neither revoked nor expired.
+pub fn get_usable_by_hash(
+    conn: &Connection,
+    key_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<ApiKey, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM api_keys
+         WHERE key_hash = ?1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > ?2);",
+    )?;
+    stmt.query_row(params![key_hash, now], api_key_from_row)
+}
+
+pub fn create(
+    conn: &Connection,
+    api_key: &mut ApiKey,
+    key_hash: &str,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO api_keys (name, prefix, key_hash, scopes, created_by, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+    )?;
+    stmt.execute(params![
+        api_key.name,
+        api_key.prefix,
+        key_hash,
+        scopes_to_sql(&api_key.scopes),
+        api_key.created_by,
+        api_key.created_at,
+        api_key.expires_at,
+    ])?;
+    api_key.id = conn.last_insert_rowid();
+    Ok(())
+}
+
+/// Only written when the previous use is older than `stale_before`, to avoid a write per request.
+pub fn touch(
+    conn: &Connection,
+    id: i64,
+    now: DateTime<Utc>,
+    stale_before: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE api_keys SET last_used_at = ?1 WHERE id = ?2 AND (last_used_at IS NULL OR last_used_at < ?3);",
+        params![now, id, stale_before],
+    )?;
+    Ok(())
+}
+
+pub fn revoke(conn: &Connection, id: i64, now: DateTime<Utc>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE api_keys SET revoked_at = ?1 WHERE id = ?2 AND revoked_at IS NULL;",
+        params![now, id],
+    )?;
+    Ok(())
+}
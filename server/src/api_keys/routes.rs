@@ -0,0 +1,17 @@
+use super::handlers;
+use crate::users::models::Role;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api-keys")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Admin))
+            .route(web::get().to(handlers::get_api_keys))
+            .route(web::post().to(handlers::post_api_key)),
+    );
+    cfg.service(
+        web::resource("/api-keys/{id}")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Admin))
+            .route(web::delete().to(handlers::delete_api_key)),
+    );
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use super::models::{ApiKey, CreatedApiKey, Scope};
+use super::repository;
+use crate::tickets::ServiceError;
+use crate::users::models::User;
+use crate::users::service::{generate_token, hash_token};
+
+/// Marks API keys so they can be told apart from session tokens, and spotted by secret scanners.
+pub const KEY_PREFIX: &str = "tk_";
+/// Characters kept in clear to recognise a key, prefix included
+const VISIBLE_LENGTH: usize = 11;
+/// Last use is only recorded once per interval, to avoid a write on every request
+const LAST_USED_RESOLUTION: chrono::Duration = chrono::Duration::minutes(1);
+
+pub fn get_api_keys(conn: &Connection) -> Result<Vec<ApiKey>, ServiceError> {
+    repository::get_all(conn).map_err(ServiceError::from)
+}
+
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub fn create_api_key(
+    conn: &Connection,
+    req: CreateApiKeyRequest,
+    user: &User,
+) -> Result<CreatedApiKey, ServiceError> {
+    if req.name.trim().is_empty() {
+        return Err(ServiceError::Invalid("Name must not be empty".to_string()));
+    }
+    if req.scopes.is_empty() {
+        return Err(ServiceError::Invalid(
+            "At least one scope is required".to_string(),
+        ));
+    }
+    let now = chrono::Utc::now();
+    if req.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(ServiceError::Invalid(
+            "Expiry must be in the future".to_string(),
+        ));
+    }
+
+    let mut scopes = Vec::new();
+    for scope in req.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let key = format!("{}{}", KEY_PREFIX, generate_token());
+    let mut api_key = ApiKey {
+        id: 0,
+        name: req.name.trim().to_string(),
+        prefix: key[..VISIBLE_LENGTH].to_string(),
+        scopes,
+        created_by: user.id,
+        created_at: now,
+        expires_at: req.expires_at,
+        last_used_at: None,
+        revoked_at: None,
+    };
+    repository::create(conn, &mut api_key, &hash_token(&key))?;
+
+    Ok(CreatedApiKey { key, api_key })
+}
+
+/// Revoking is permanent, revoked keys are kept so that their past actions stay attributable.
+pub fn revoke_api_key(conn: &Connection, id: i64) -> Result<ApiKey, ServiceError> {
+    repository::get_by_id(conn, id)?;
+    repository::revoke(conn, id, chrono::Utc::now())?;
+    repository::get_by_id(conn, id).map_err(ServiceError::from)
+}
+
+/// Resolves a key to its record, `Unauthorized` if it is unknown, revoked or expired.
+pub fn authenticate(conn: &Connection, key: &str) -> Result<ApiKey, ServiceError> {
+    let now = chrono::Utc::now();
+    let mut api_key = match repository::get_usable_by_hash(conn, &hash_token(key), now) {
+        Ok(api_key) => api_key,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(ServiceError::Unauthorized),
+        Err(e) => return Err(e.into()),
+    };
+    repository::touch(conn, api_key.id, now, now - LAST_USED_RESOLUTION)?;
+    if api_key
+        .last_used_at
+        .is_none_or(|last_used_at| last_used_at < now - LAST_USED_RESOLUTION)
+    {
+        api_key.last_used_at = Some(now);
+    }
+    Ok(api_key)
+}
@@ -0,0 +1,129 @@
+use std::io::BufRead;
+
+use crate::config::DatabaseConfig;
+use crate::users::models::Role;
+use crate::users::service::{self as users, CreateUserRequest, UpdateUserRequest};
+use crate::utils::db::{self, Connection};
+use crate::utils::migrations;
+
+const USAGE: &str = "Usage:
+    ticketing-api [migrate [status]]
+    ticketing-api user create <email> <name> <admin|agent|viewer>
+    ticketing-api user password <email>
+    ticketing-api user list
+
+Passwords are read from the standard input.";
+
+/// Runs the command given on the command line, if any.
+/// Returns `None` when the HTTP server should be started instead.
+pub fn run(args: &[String]) -> Option<Result<(), String>> {
+    if args.is_empty() {
+        return None;
+    }
+    Some(connect().and_then(|conn| dispatch(&conn, args)))
+}
+
+/// Commands only need the database, the mail and server settings may be missing.
+fn connect() -> Result<Connection, String> {
+    let config = DatabaseConfig::load().map_err(|e| e.to_string())?;
+    let pool = db::create_pool(&config).map_err(|e| e.to_string())?;
+    pool.get().map_err(|e| e.to_string())
+}
+
+fn dispatch(conn: &Connection, args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["migrate"] => migrate(conn),
+        ["migrate", "status"] => migrate_status(conn),
+        ["user", "create", email, name, role] => user_create(conn, email, name, role),
+        ["user", "password", email] => user_password(conn, email),
+        ["user", "list"] => user_list(conn),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<(), String> {
+    let applied = migrations::migrate(conn).map_err(|e| e.to_string())?;
+    if applied.is_empty() {
+        println!("Database is up to date");
+    }
+    for migration in applied {
+        println!("Applied {:04}_{}", migration.version, migration.name);
+    }
+    Ok(())
+}
+
+fn migrate_status(conn: &Connection) -> Result<(), String> {
+    for migration in migrations::status(conn).map_err(|e| e.to_string())? {
+        let state = match migration.applied_at {
+            Some(applied_at) => format!("applied {}", applied_at.to_rfc3339()),
+            None => "pending".to_string(),
+        };
+        println!("{:04}_{:<32} {}", migration.version, migration.name, state);
+    }
+    Ok(())
+}
+
+fn read_password() -> Result<String, String> {
+    eprint!("Password: ");
+    let mut password = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut password)
+        .map_err(|e| e.to_string())?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn user_create(conn: &Connection, email: &str, name: &str, role: &str) -> Result<(), String> {
+    // Users are stored in tables created by the migrations
+    migrations::migrate(conn).map_err(|e| e.to_string())?;
+    let role: Role = role.parse()?;
+    let user = users::create_user(
+        conn,
+        CreateUserRequest {
+            email: email.to_string(),
+            name: name.to_string(),
+            password: read_password()?,
+            role,
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    println!("Created {} user {} ({})", user.role, user.email, user.id);
+    Ok(())
+}
+
+fn user_password(conn: &Connection, email: &str) -> Result<(), String> {
+    migrations::migrate(conn).map_err(|e| e.to_string())?;
+    let user = users::get_user_by_email(conn, email).map_err(|e| e.to_string())?;
+    users::update_user(
+        conn,
+        user.id,
+        UpdateUserRequest {
+            name: None,
+            role: None,
+            active: None,
+            password: Some(read_password()?),
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    println!(
+        "Password of {} changed, existing sessions were revoked",
+        user.email
+    );
+    Ok(())
+}
+
+fn user_list(conn: &Connection) -> Result<(), String> {
+    migrations::migrate(conn).map_err(|e| e.to_string())?;
+    for user in users::get_users(conn).map_err(|e| e.to_string())? {
+        println!(
+            "{:>4} {:<32} {:<24} {:<6} {}",
+            user.id,
+            user.email,
+            user.name,
+            user.role,
+            if user.active { "active" } else { "inactive" }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::outbox::worker::OutboxSettings;
+use crate::portal::models::PortalSettings;
+use crate::tickets::numbering::{self, NumberFormat};
+use crate::utils::lockout::{self, LockoutSettings};
+use crate::utils::mailer::smtp::{SmtpSecurity, SmtpSettings};
+use crate::utils::mailer::Mailbox;
+use crate::utils::public_url::PublicUrl;
+use crate::utils::validation::{self, ValidationSettings};
+
+/// Read when `CONFIG_FILE` is not set, ignored if it does not exist
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+/// Longest accepted duration, ten years. Expiry dates are computed by adding durations
+/// to the current time, which must not overflow.
+const MAX_SECONDS: u64 = 10 * 365 * 24 * 60 * 60;
+
+/// Every setting, by environment variable name. In the TOML file the same settings
+/// are written in lowercase, either flat (`smtp_host = ...`) or grouped in a table
+/// named after the prefix (`[smtp]` then `host = ...`).
+const SETTINGS: &[&str] = &[
+    "HOST",
+    "PORT",
+    "STATIC_DIR",
+    "DATABASE_PATH",
+    "DATABASE_POOL_SIZE",
+    "DATABASE_CONNECT_TIMEOUT",
+    "DATABASE_IDLE_TIMEOUT",
+    "DATABASE_BUSY_TIMEOUT",
+    "DATABASE_CACHE_SIZE",
+    "DATABASE_MAINTENANCE_INTERVAL",
+    "SESSION_TTL",
+    "AUTH_MAX_FAILURES",
+    "AUTH_LOCKOUT",
+    "AUTH_TRUSTED_PROXIES",
+    "PUBLIC_BASE_URL",
+    "TICKET_NUMBER_FORMAT",
+    "TICKET_LINK_TTL",
+    "REOPEN_WINDOW",
+    "EMAIL_BLOCKED_DOMAINS",
+    "PORTAL_LINK_TTL",
+    "PORTAL_SESSION_TTL",
+    "TEMPLATE_DIR",
+    "SENDER_NAME",
+    "SENDER_EMAIL",
+    "MAIL_BACKEND",
+    "MAIL_DIR",
+    "BREVO_API_KEY",
+    "SMTP_HOST",
+    "SMTP_PORT",
+    "SMTP_SECURITY",
+    "SMTP_USERNAME",
+    "SMTP_PASSWORD",
+    "OUTBOX_MAX_ATTEMPTS",
+    "OUTBOX_POLL_INTERVAL",
+    "OUTBOX_RETRY_BASE",
+    "OUTBOX_RETENTION",
+];
+
+/// A value that must never end up in logs, `Debug` and `Display` only print a placeholder.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[redacted]")
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[redacted]")
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    File { path: PathBuf, reason: String },
+    UnknownSetting { path: PathBuf, key: String },
+    Missing(&'static str),
+    Invalid { name: &'static str, reason: String },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::File { path, reason } => {
+                write!(f, "Cannot read config file {}: {}", path.display(), reason)
+            }
+            ConfigError::UnknownSetting { path, key } => {
+                write!(f, "Unknown setting {} in {}", key, path.display())
+            }
+            ConfigError::Missing(name) => write!(
+                f,
+                "{} must be set, either in the environment or as {} in the config file",
+                name,
+                name.to_lowercase()
+            ),
+            ConfigError::Invalid { name, reason } => write!(f, "Invalid {}: {}", name, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub host: IpAddr,
+    pub port: u16,
+    /// Built frontend served on `/`
+    pub static_dir: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub path: PathBuf,
+    /// Connections kept open at most
+    pub pool_size: u32,
+    /// How long a request waits for a free connection before getting a `503`
+    pub connect_timeout: Duration,
+    /// Idle connections are closed after this long
+    pub idle_timeout: Option<Duration>,
+    /// How long a write waits for another one to finish before failing
+    pub busy_timeout: Duration,
+    /// Page cache of each connection, in KiB
+    pub cache_size: u32,
+    /// How often the write-ahead log is checkpointed and the query planner statistics refreshed
+    pub maintenance_interval: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub enum MailBackend {
+    Brevo {
+        api_key: Secret,
+    },
+    Smtp(SmtpSettings),
+    /// Maildir drop, for development
+    File {
+        dir: PathBuf,
+    },
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    pub sender: Mailbox,
+    pub backend: MailBackend,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    /// Lifetime of a staff session
+    pub session_ttl: Duration,
+    /// Lockout of addresses that keep failing to authenticate
+    pub lockout: LockoutSettings,
+    pub public_url: PublicUrl,
+    pub number_format: NumberFormat,
+    /// Lifetime of the ticket links emailed to requesters
+    pub ticket_link_ttl: Duration,
+    /// How long after closing a requester may reopen their ticket
+    pub reopen_window: Duration,
+    /// Rules applied to what requesters submit
+    pub validation: ValidationSettings,
+    pub portal: PortalSettings,
+    /// Overrides for the built-in email templates
+    pub template_dir: Option<PathBuf>,
+    pub mail: MailConfig,
+    pub outbox: OutboxSettings,
+}
+
+/// Raw settings, environment variables take precedence over the config file.
+struct Source {
+    file: HashMap<String, String>,
+}
+
+impl Source {
+    fn get(&self, name: &'static str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .or_else(|| self.file.get(name).cloned())
+            .filter(|value| !value.trim().is_empty())
+    }
+
+    fn required(&self, name: &'static str) -> Result<String, ConfigError> {
+        self.get(name).ok_or(ConfigError::Missing(name))
+    }
+
+    fn parse<T: FromStr>(&self, name: &'static str) -> Result<Option<T>, ConfigError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.get(name)
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|e: T::Err| ConfigError::Invalid {
+                        name,
+                        reason: format!("{}: {}", value, e),
+                    })
+            })
+            .transpose()
+    }
+
+    fn seconds(&self, name: &'static str, default: u64) -> Result<Duration, ConfigError> {
+        let seconds = self.parse(name)?.unwrap_or(default);
+        if seconds > MAX_SECONDS {
+            return Err(ConfigError::Invalid {
+                name,
+                reason: format!("{} is more than {} seconds", seconds, MAX_SECONDS),
+            });
+        }
+        Ok(Duration::from_secs(seconds))
+    }
+
+    fn with<T>(
+        &self,
+        name: &'static str,
+        parse: impl FnOnce(&str) -> Result<T, String>,
+    ) -> Result<T, ConfigError> {
+        parse(&self.required(name)?).map_err(|reason| ConfigError::Invalid { name, reason })
+    }
+}
+
+/// Flattens the TOML tables into setting names, `[smtp] host` becomes `SMTP_HOST`.
+fn flatten(
+    prefix: &str,
+    table: toml::Table,
+    path: &Path,
+    settings: &mut HashMap<String, String>,
+) -> Result<(), ConfigError> {
+    for (key, value) in table {
+        let name = match prefix {
+            "" => key.to_uppercase(),
+            _ => format!("{}_{}", prefix, key.to_uppercase()),
+        };
+        let value = match value {
+            toml::Value::Table(table) => {
+                flatten(&name, table, path, settings)?;
+                continue;
+            }
+            toml::Value::String(value) => value,
+            toml::Value::Integer(value) => value.to_string(),
+            toml::Value::Float(value) => value.to_string(),
+            toml::Value::Boolean(value) => value.to_string(),
+            toml::Value::Datetime(value) => value.to_string(),
+            toml::Value::Array(_) => {
+                return Err(ConfigError::File {
+                    path: path.to_path_buf(),
+                    reason: format!("{} cannot be a list", name.to_lowercase()),
+                })
+            }
+        };
+        if !SETTINGS.contains(&name.as_str()) {
+            return Err(ConfigError::UnknownSetting {
+                path: path.to_path_buf(),
+                key: name.to_lowercase(),
+            });
+        }
+        settings.insert(name, value);
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<HashMap<String, String>, ConfigError> {
+    let error = |reason: String| ConfigError::File {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let content = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e: toml::de::Error| error(e.to_string()))?;
+
+    let mut settings = HashMap::new();
+    flatten("", table, path, &mut settings)?;
+    Ok(settings)
+}
+
+fn default_static_dir() -> PathBuf {
+    let mut dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    dir.push("static");
+    dir
+}
+
+impl Source {
+    /// Reads the config file named by `CONFIG_FILE` (or `config.toml` if present).
+    fn load() -> Result<Self, ConfigError> {
+        let file = match std::env::var("CONFIG_FILE") {
+            Ok(path) => read_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).is_file() => {
+                read_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            Err(_) => HashMap::new(),
+        };
+        Ok(Self { file })
+    }
+}
+
+impl Config {
+    /// Reads the config file and the environment, and validates every setting.
+    pub fn load() -> Result<Self, ConfigError> {
+        let source = Source::load()?;
+        if std::env::var("ADMIN_TOKEN").is_ok() {
+            log::warn!("ADMIN_TOKEN is no longer used, staff log in with their own account");
+        }
+        Self::from_source(&source)
+    }
+
+    fn from_source(source: &Source) -> Result<Self, ConfigError> {
+        Ok(Self {
+            server: ServerConfig {
+                host: source
+                    .parse("HOST")?
+                    .unwrap_or(IpAddr::from([127, 0, 0, 1])),
+                port: source.parse("PORT")?.unwrap_or(8080),
+                static_dir: source
+                    .get("STATIC_DIR")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(default_static_dir),
+            },
+            database: DatabaseConfig::from_source(source)?,
+            session_ttl: source.seconds("SESSION_TTL", 12 * 60 * 60)?,
+            lockout: LockoutSettings {
+                max_failures: source.parse("AUTH_MAX_FAILURES")?.unwrap_or(10).max(1),
+                duration: source.seconds("AUTH_LOCKOUT", 15 * 60)?,
+                trusted_proxies: source
+                    .get("AUTH_TRUSTED_PROXIES")
+                    .map(|addresses| lockout::parse_addresses(&addresses))
+                    .transpose()
+                    .map_err(|reason| ConfigError::Invalid {
+                        name: "AUTH_TRUSTED_PROXIES",
+                        reason,
+                    })?
+                    .unwrap_or_default(),
+            },
+            public_url: source.with("PUBLIC_BASE_URL", PublicUrl::parse)?,
+            number_format: NumberFormat::parse(
+                &source
+                    .get("TICKET_NUMBER_FORMAT")
+                    .unwrap_or_else(|| numbering::DEFAULT_FORMAT.to_string()),
+            )
+            .map_err(|reason| ConfigError::Invalid {
+                name: "TICKET_NUMBER_FORMAT",
+                reason,
+            })?,
+            ticket_link_ttl: source.seconds("TICKET_LINK_TTL", 90 * 24 * 60 * 60)?,
+            reopen_window: source.seconds("REOPEN_WINDOW", 14 * 24 * 60 * 60)?,
+            validation: ValidationSettings {
+                blocked_email_domains: source
+                    .get("EMAIL_BLOCKED_DOMAINS")
+                    .map(|domains| validation::parse_domains(&domains))
+                    .transpose()
+                    .map_err(|reason| ConfigError::Invalid {
+                        name: "EMAIL_BLOCKED_DOMAINS",
+                        reason,
+                    })?
+                    .unwrap_or_default(),
+            },
+            portal: PortalSettings {
+                link_ttl: source.seconds("PORTAL_LINK_TTL", 15 * 60)?,
+                session_ttl: source.seconds("PORTAL_SESSION_TTL", 60 * 60)?,
+            },
+            template_dir: source.get("TEMPLATE_DIR").map(PathBuf::from),
+            mail: MailConfig::from_source(source)?,
+            outbox: OutboxSettings {
+                max_attempts: source.parse("OUTBOX_MAX_ATTEMPTS")?.unwrap_or(8).max(1),
+                poll_interval: source
+                    .seconds("OUTBOX_POLL_INTERVAL", 5)?
+                    .max(Duration::from_secs(1)),
+                retry_base: source.seconds("OUTBOX_RETRY_BASE", 30)?,
+                retention: source.seconds("OUTBOX_RETENTION", 30 * 24 * 60 * 60)?,
+            },
+        })
+    }
+}
+
+/// Both or neither, a username alone would silently send without authenticating.
+fn smtp_credentials(source: &Source) -> Result<Option<(String, Secret)>, ConfigError> {
+    match (source.get("SMTP_USERNAME"), source.get("SMTP_PASSWORD")) {
+        (Some(username), Some(password)) => Ok(Some((username, Secret::new(password)))),
+        (None, None) => Ok(None),
+        (Some(_), None) => Err(ConfigError::Invalid {
+            name: "SMTP_USERNAME",
+            reason: "SMTP_PASSWORD must be set along with it".to_string(),
+        }),
+        (None, Some(_)) => Err(ConfigError::Invalid {
+            name: "SMTP_PASSWORD",
+            reason: "SMTP_USERNAME must be set along with it".to_string(),
+        }),
+    }
+}
+
+impl DatabaseConfig {
+    /// Only the database settings, for the command line tools that need nothing else.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_source(&Source::load()?)
+    }
+
+    fn from_source(source: &Source) -> Result<Self, ConfigError> {
+        Ok(Self {
+            path: source
+                .get("DATABASE_PATH")
+                .unwrap_or_else(|| "tickets.sqlite3".to_string())
+                .into(),
+            pool_size: source.parse("DATABASE_POOL_SIZE")?.unwrap_or(10).max(1),
+            connect_timeout: source
+                .seconds("DATABASE_CONNECT_TIMEOUT", 5)?
+                .max(Duration::from_secs(1)),
+            // 0 keeps idle connections open
+            idle_timeout: Some(source.seconds("DATABASE_IDLE_TIMEOUT", 10 * 60)?)
+                .filter(|timeout| !timeout.is_zero()),
+            // SQLite takes the timeout in milliseconds as a 32 bit integer
+            busy_timeout: source
+                .seconds("DATABASE_BUSY_TIMEOUT", 5)?
+                .min(Duration::from_secs(60 * 60)),
+            cache_size: source.parse("DATABASE_CACHE_SIZE")?.unwrap_or(8 * 1024),
+            // 0 disables the maintenance
+            maintenance_interval: Some(source.seconds("DATABASE_MAINTENANCE_INTERVAL", 60 * 60)?)
+                .filter(|interval| !interval.is_zero()),
+        })
+    }
+}
+
+impl MailConfig {
+    fn from_source(source: &Source) -> Result<Self, ConfigError> {
+        let sender = Mailbox {
+            name: source.required("SENDER_NAME")?,
+            email: source.required("SENDER_EMAIL")?,
+        };
+
+        let backend = match source.get("MAIL_BACKEND").as_deref().unwrap_or("brevo") {
+            "brevo" => MailBackend::Brevo {
+                api_key: Secret::new(source.required("BREVO_API_KEY")?),
+            },
+            "smtp" => MailBackend::Smtp(SmtpSettings {
+                host: source.required("SMTP_HOST")?,
+                port: source.parse("SMTP_PORT")?,
+                security: source
+                    .parse("SMTP_SECURITY")?
+                    .unwrap_or(SmtpSecurity::StartTls),
+                credentials: smtp_credentials(source)?,
+            }),
+            "file" => MailBackend::File {
+                dir: source
+                    .get("MAIL_DIR")
+                    .unwrap_or_else(|| "mail".to_string())
+                    .into(),
+            },
+            "memory" => MailBackend::Memory,
+            other => {
+                return Err(ConfigError::Invalid {
+                    name: "MAIL_BACKEND",
+                    reason: format!("{} is not one of brevo, smtp, file or memory", other),
+                })
+            }
+        };
+
+        Ok(Self { sender, backend })
+    }
+}
@@ -0,0 +1,15 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use uuid::Uuid;
+
+use super::service;
+use crate::utils::db;
+use crate::Pool;
+
+pub async fn get_history(db: web::Data<Pool>, path: web::Path<Uuid>) -> impl Responder {
+    let id = path.into_inner();
+
+    match db::run(&db, move |conn| service::get_history(conn, id)).await {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,9 @@
+pub mod handlers;
+pub mod models;
+mod repository;
+pub mod routes;
+pub(crate) mod service;
+
+/// Actor recorded for changes made by the ticket requester, changes made by staff
+/// record the email of the user and those made by integrations `api_key:<id>`.
+pub const ACTOR_REQUESTER: &str = "requester";
@@ -0,0 +1,46 @@
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use super::models::TicketEvent;
+use crate::tickets::repository::parse_uuid;
+
+pub fn get_by_ticket(
+    conn: &Connection,
+    ticket_id: &Uuid,
+) -> Result<Vec<TicketEvent>, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT * FROM ticket_events WHERE ticket_uuid = ?1 ORDER BY id;")?;
+    stmt.query_map([ticket_id.to_string()], |row| {
+        Ok(TicketEvent {
+            id: row.get("id")?,
+            ticket_uuid: {
+                let uuid_str: String = row.get("ticket_uuid")?;
+                parse_uuid(&uuid_str)?
+            },
+            field: row.get("field")?,
+            old_value: row.get("old_value")?,
+            new_value: row.get("new_value")?,
+            actor: row.get("actor")?,
+            created_at: row.get("created_at")?,
+        })
+    })
+    .and_then(Iterator::collect)
+}
+
+pub fn create(conn: &Connection, event: &TicketEvent) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO ticket_events (ticket_uuid, field, old_value, new_value, actor, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+    )?;
+
+    stmt.execute(params![
+        event.ticket_uuid.to_string(),
+        event.field,
+        event.old_value,
+        event.new_value,
+        event.actor,
+        event.created_at,
+    ])?;
+
+    Ok(())
+}
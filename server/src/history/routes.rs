@@ -0,0 +1,13 @@
+use super::handlers;
+use crate::api_keys::models::Scope;
+use crate::middlewares::auth::RequireRole;
+use crate::users::models::Role;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/tickets/{id}/history")
+            .wrap(RequireRole::new(Role::Viewer).or_scope(Scope::TicketsRead))
+            .route(web::get().to(handlers::get_history)),
+    );
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use super::models::TicketEvent;
+use super::repository;
+use crate::tickets::models::Ticket;
+use crate::tickets::{repository as tickets_repository, ServiceError};
+
+pub fn get_history(conn: &Connection, ticket_id: Uuid) -> Result<Vec<TicketEvent>, ServiceError> {
+    let events = repository::get_by_ticket(conn, &ticket_id)?;
+    // Deleted tickets keep their history, and tickets untouched since history was
+    // introduced have none, so only a missing ticket without history is unknown
+    if events.is_empty() {
+        tickets_repository::get_by_id(conn, ticket_id)?;
+    }
+    Ok(events)
+}
+
+pub fn record(
+    conn: &Connection,
+    ticket_id: Uuid,
+    field: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    actor: &str,
+    at: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    repository::create(
+        conn,
+        &TicketEvent {
+            id: 0,
+            ticket_uuid: ticket_id,
+            field: field.to_string(),
+            old_value,
+            new_value,
+            actor: actor.to_string(),
+            created_at: at,
+        },
+    )
+}
+
+/// Records one event per field that differs between the two versions of the ticket.
+pub fn record_changes(
+    conn: &Connection,
+    before: &Ticket,
+    after: &Ticket,
+    actor: &str,
+    at: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    let fields = [
+        ("name", Some(&before.name), Some(&after.name)),
+        ("email", Some(&before.email), Some(&after.email)),
+        ("message", Some(&before.message), Some(&after.message)),
+        ("note", before.note.as_ref(), after.note.as_ref()),
+    ];
+    for (field, old_value, new_value) in fields {
+        if old_value != new_value {
+            record(
+                conn,
+                after.uuid,
+                field,
+                old_value.cloned(),
+                new_value.cloned(),
+                actor,
+                at,
+            )?;
+        }
+    }
+
+    if before.status != after.status {
+        record(
+            conn,
+            after.uuid,
+            "status",
+            Some(before.status.to_string()),
+            Some(after.status.to_string()),
+            actor,
+            at,
+        )?;
+    }
+    Ok(())
+}
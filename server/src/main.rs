@@ -7,16 +7,31 @@ use actix_web::{
 };
 use dotenv::dotenv;
 use env_logger::Env;
-use r2d2_sqlite::SqliteConnectionManager;
 
+use crate::config::Config;
+use crate::middlewares::request_id::RequestId;
+use crate::outbox::worker;
 use crate::utils::{
-    db::{init_db, Pool},
-    get_listen_address,
+    db::{self, create_pool, init_db, Pool},
+    lockout::Lockout,
+    mailer,
+    notifications::Notifications,
+    problem,
+    templates::Templates,
 };
 
+mod api_keys;
+mod cli;
+mod config;
+mod history;
+mod messages;
 mod middlewares;
+mod outbox;
+mod portal;
+mod requester;
 mod status;
 mod tickets;
+mod users;
 mod utils;
 
 #[actix_web::main]
@@ -24,25 +39,64 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init_from_env(Env::default().default_filter_or("info"));
 
-    let manager = SqliteConnectionManager::file("tickets.sqlite3");
-    let pool = Pool::new(manager).unwrap();
-    match init_db(&pool.get().expect("Failed to get DB connection")) {
-        Ok(_) => log::info!("Database initialized successfully"),
-        Err(e) => log::error!("Failed to initialize database: {}", e),
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = cli::run(&args) {
+        return result.map_err(std::io::Error::other);
     }
 
-    // Get the static directory path
-    let mut static_dir = std::env::current_exe()?;
-    static_dir.pop();
-    static_dir.push("static");
+    // Missing or invalid settings are fatal, nothing runs on a guessed configuration
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Invalid configuration: {}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+    log::debug!("Loaded configuration: {:#?}", config);
 
-    let address = get_listen_address();
+    let pool = match create_pool(&config.database) {
+        Ok(pool) => pool,
+        Err(e) => {
+            log::error!(
+                "Cannot open database {}: {}",
+                config.database.path.display(),
+                e
+            );
+            return Err(std::io::Error::other(e));
+        }
+    };
+    let conn = pool.get().map_err(std::io::Error::other)?;
 
-    log::info!("Starting HTTP server at http://{}:8080", address);
+    // Refuse to start on a schema we don't know how to handle
+    if let Err(e) = init_db(&conn) {
+        log::error!("Failed to initialize database: {}", e);
+        return Err(std::io::Error::other(e));
+    }
+    log::info!("Database initialized successfully");
+    drop(conn);
+
+    log::info!("Public base URL is {}", config.public_url);
+    let notifications = web::Data::new(Notifications::new(
+        Templates::load(config.template_dir.clone()).map_err(std::io::Error::other)?,
+        config.public_url.clone(),
+        config.ticket_link_ttl,
+    ));
+    let mailer = mailer::from_config(&config.mail).map_err(std::io::Error::other)?;
+
+    if let Some(interval) = config.database.maintenance_interval {
+        db::spawn_maintenance(pool.clone(), interval);
+    }
+    // Emails are queued by the request handlers and delivered in the background
+    worker::spawn(pool.clone(), mailer, config.outbox.clone());
+
+    let address = (config.server.host, config.server.port);
+    log::info!("Starting HTTP server at http://{}:{}", address.0, address.1);
+    let lockout = web::Data::new(Lockout::new(config.lockout.clone()));
+    let config = web::Data::new(config);
 
     HttpServer::new(move || {
         let cors = Cors::default()
-            .allowed_origin("https://ticket.matheo-galuba.com")
+            .allowed_origin(&config.public_url.origin())
             // .allow_any_origin()
             .allowed_methods(vec!["GET", "POST", "PATCH", "DELETE", "OPTIONS"])
             .allowed_headers(vec![
@@ -54,23 +108,39 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(config.clone())
+            .app_data(web::Data::new(config.number_format.clone()))
+            .app_data(notifications.clone())
+            .app_data(lockout.clone())
+            // Malformed requests are answered with problem details like every other error
+            .app_data(web::JsonConfig::default().error_handler(problem::json_error))
+            .app_data(web::PathConfig::default().error_handler(problem::path_error))
+            .app_data(web::QueryConfig::default().error_handler(problem::query_error))
             .wrap(cors)
             .wrap(NormalizePath::trim())
             .wrap(Logger::new(
-                "%a %t \"%r\" %s %b \"%{referer}i\" \"%{user-agent}i\" %t",
+                "%a %t \"%r\" %s %b \"%{referer}i\" \"%{user-agent}i\" %{x-request-id}o",
             ))
             .service(
                 web::scope("/api")
+                    .wrap(RequestId)
                     .configure(status::routes::configure)
-                    .configure(tickets::routes::configure),
+                    .configure(users::routes::configure)
+                    .configure(api_keys::routes::configure)
+                    .configure(tickets::routes::configure)
+                    .configure(requester::routes::configure)
+                    .configure(portal::routes::configure)
+                    .configure(messages::routes::configure)
+                    .configure(history::routes::configure)
+                    .configure(outbox::routes::configure),
             )
             .service(
-                Files::new("/", &static_dir)
+                Files::new("/", &config.server.static_dir)
                     .use_last_modified(true)
                     .index_file("index.html"),
             )
     })
-    .bind((address, 8080))?
+    .bind(address)?
     .run()
     .await
 }
@@ -0,0 +1,50 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::models::MessageKind;
+use super::service::{self, CreateMessageRequest};
+use crate::users::models::Principal;
+use crate::utils::db;
+use crate::utils::notifications::Notifications;
+use crate::Pool;
+
+pub async fn get_messages(db: web::Data<Pool>, path: web::Path<Uuid>) -> impl Responder {
+    let id = path.into_inner();
+
+    match db::run(&db, move |conn| service::get_messages(conn, id)).await {
+        Ok(messages) => HttpResponse::Ok().json(messages),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostMessage {
+    kind: MessageKind,
+    body: String,
+}
+
+pub async fn post_message(
+    db: web::Data<Pool>,
+    notifications: web::Data<Notifications>,
+    principal: Principal,
+    path: web::Path<Uuid>,
+    body: web::Json<PostMessage>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let body = body.into_inner();
+
+    let req = CreateMessageRequest {
+        kind: body.kind,
+        body: body.body,
+    };
+
+    match db::run(&db, move |conn| {
+        service::add_message(conn, &notifications, id, req, &principal)
+    })
+    .await
+    {
+        Ok(message) => HttpResponse::Created().json(message),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,5 @@
+pub mod handlers;
+pub mod models;
+pub(crate) mod repository;
+pub mod routes;
+mod service;
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    /// Written by the requester
+    RequesterReply,
+    /// Written by staff and emailed to the requester
+    StaffReply,
+    /// Written by staff, never shown to the requester
+    InternalNote,
+}
+
+impl MessageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageKind::RequesterReply => "requester_reply",
+            MessageKind::StaffReply => "staff_reply",
+            MessageKind::InternalNote => "internal_note",
+        }
+    }
+}
+
+impl std::str::FromStr for MessageKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "requester_reply" => Ok(MessageKind::RequesterReply),
+            "staff_reply" => Ok(MessageKind::StaffReply),
+            "internal_note" => Ok(MessageKind::InternalNote),
+            other => Err(format!("Unknown message kind: {}", other)),
+        }
+    }
+}
+
+impl ToSql for MessageKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for MessageKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketMessage {
+    pub id: i64,
+    pub ticket_uuid: Uuid,
+    pub kind: MessageKind,
+    pub author_name: String,
+    pub author_email: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,56 @@
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use super::models::TicketMessage;
+use crate::tickets::repository::parse_uuid;
+
+fn message_from_row(row: &rusqlite::Row) -> Result<TicketMessage, rusqlite::Error> {
+    Ok(TicketMessage {
+        id: row.get("id")?,
+        ticket_uuid: {
+            let uuid_str: String = row.get("ticket_uuid")?;
+            parse_uuid(&uuid_str)?
+        },
+        kind: row.get("kind")?,
+        author_name: row.get("author_name")?,
+        author_email: row.get("author_email")?,
+        body: row.get("body")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub fn get_by_ticket(
+    conn: &Connection,
+    ticket_id: &Uuid,
+) -> Result<Vec<TicketMessage>, rusqlite::Error> {
+    let mut stmt = conn
+        .prepare("SELECT * FROM ticket_messages WHERE ticket_uuid = ?1 ORDER BY created_at, id;")?;
+    stmt.query_map([ticket_id.to_string()], message_from_row)
+        .and_then(Iterator::collect)
+}
+
+/// Inserts the message and fills in its generated id.
+pub fn create(conn: &Connection, message: &mut TicketMessage) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO ticket_messages (ticket_uuid, kind, author_name, author_email, body, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+    )?;
+
+    stmt.execute(params![
+        message.ticket_uuid.to_string(),
+        message.kind,
+        message.author_name,
+        message.author_email,
+        message.body,
+        message.created_at,
+    ])?;
+    message.id = conn.last_insert_rowid();
+
+    Ok(())
+}
+
+pub fn delete_by_ticket(conn: &Connection, ticket_id: &Uuid) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare("DELETE FROM ticket_messages WHERE ticket_uuid = ?1;")?;
+    stmt.execute([ticket_id.to_string()])?;
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use super::handlers;
+use crate::api_keys::models::Scope;
+use crate::middlewares::auth::RequireRole;
+use crate::users::models::Role;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/tickets/{id}/messages")
+            .route(
+                web::get()
+                    .to(handlers::get_messages)
+                    .wrap(RequireRole::new(Role::Viewer).or_scope(Scope::TicketsRead)),
+            )
+            .route(
+                web::post()
+                    .to(handlers::post_message)
+                    .wrap(RequireRole::new(Role::Agent).or_scope(Scope::TicketsWrite)),
+            ),
+    );
+}
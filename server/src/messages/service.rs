@@ -0,0 +1,82 @@
+use rusqlite::{Transaction, TransactionBehavior};
+use uuid::Uuid;
+
+use super::models::{MessageKind, TicketMessage};
+use super::repository;
+use crate::history::service as history;
+use crate::outbox::service as outbox;
+use crate::requester::service as requester;
+use crate::tickets::{repository as tickets_repository, ServiceError};
+use crate::users::models::Principal;
+use crate::utils::db::Connection;
+use crate::utils::notifications::Notifications;
+
+pub fn get_messages(
+    conn: &Connection,
+    ticket_id: Uuid,
+) -> Result<Vec<TicketMessage>, ServiceError> {
+    // Distinguish an unknown ticket from a ticket without messages
+    tickets_repository::get_by_id(conn, ticket_id)?;
+    repository::get_by_ticket(conn, &ticket_id).map_err(ServiceError::from)
+}
+
+pub struct CreateMessageRequest {
+    pub kind: MessageKind,
+    pub body: String,
+}
+
+pub fn add_message(
+    conn: &Connection,
+    notifications: &Notifications,
+    ticket_id: Uuid,
+    req: CreateMessageRequest,
+    principal: &Principal,
+) -> Result<TicketMessage, ServiceError> {
+    // Read under the write lock, writing back a stale row would revert concurrent changes
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let mut ticket = tickets_repository::get_by_id(&tx, ticket_id)?;
+    let now = chrono::Utc::now();
+
+    let (author_name, author_email) = match req.kind {
+        MessageKind::RequesterReply => (ticket.name.clone(), Some(ticket.email.clone())),
+        MessageKind::StaffReply | MessageKind::InternalNote => (
+            principal.name().to_string(),
+            principal.email().map(str::to_string),
+        ),
+    };
+
+    let mut message = TicketMessage {
+        id: 0,
+        ticket_uuid: ticket.uuid,
+        kind: req.kind,
+        author_name,
+        author_email,
+        body: req.body,
+        created_at: now,
+    };
+
+    repository::create(&tx, &mut message)?;
+    ticket.updated_at = Some(now);
+    tickets_repository::update(&tx, &ticket.uuid, &ticket)?;
+    history::record(
+        &tx,
+        ticket.uuid,
+        message.kind.as_str(),
+        None,
+        Some(message.id.to_string()),
+        &principal.actor(),
+        now,
+    )?;
+    // Public staff replies are emailed to the requester
+    if message.kind == MessageKind::StaffReply {
+        let token = requester::issue_token(&tx, ticket.uuid, now, notifications.link_ttl())?;
+        outbox::enqueue(
+            &tx,
+            &notifications.reply_email(&ticket, &message, &token)?,
+            now,
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(message)
+}
@@ -1,47 +1,178 @@
-use std::future::{ready, Ready};
 use actix_web::{
-    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
     body::EitherBody,
-    Error,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{self, HeaderMap},
+        StatusCode,
+    },
+    web, Error, HttpMessage, HttpResponse, ResponseError,
 };
 use futures_util::future::LocalBoxFuture;
-use futures_util::{TryFutureExt, FutureExt};
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::api_keys::models::Scope;
+use crate::api_keys::service::{self as api_keys, KEY_PREFIX};
+use crate::tickets::ServiceError;
+use crate::users::models::{Principal, Role};
+use crate::users::service as users;
+use crate::utils::db::{self, Pool};
+use crate::utils::lockout::Lockout;
+use crate::utils::problem::Problem;
+
+const REALM: &str = "ticketing";
+
+/// Extracts the session token or API key from an `Authorization: Bearer <token>` header.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// `401` telling the client how to authenticate, with the RFC 6750 error code if any.
+pub fn unauthorized(error: Option<&'static str>) -> HttpResponse {
+    let (challenge, problem) = match error {
+        Some(error) => (
+            format!("Bearer realm=\"{}\", error=\"{}\"", REALM, error),
+            Problem::new(StatusCode::UNAUTHORIZED, error).detail("The token is unknown or expired"),
+        ),
+        None => (
+            format!("Bearer realm=\"{}\"", REALM),
+            Problem::new(StatusCode::UNAUTHORIZED, "unauthorized")
+                .detail("Credentials are required"),
+        ),
+    };
+    problem
+        .header(header::WWW_AUTHENTICATE, challenge)
+        .response()
+}
+
+pub fn locked_out(retry_after: Duration) -> HttpResponse {
+    Problem::new(StatusCode::TOO_MANY_REQUESTS, "locked_out")
+        .detail("Too many failed attempts, try again later")
+        .header(
+            header::RETRY_AFTER,
+            retry_after.as_secs().max(1).to_string(),
+        )
+        .response()
+}
 
 // There are two steps in middleware processing.
 // 1. Middleware initialization, middleware factory gets called with
 //    next service in chain as parameter.
 // 2. Middleware's call method gets called with normal request.
-pub struct AdminAuth;
+//
+// Authenticates the session token or API key, and rejects users below the given role
+// and keys without the given scope. Routes that do not name a scope refuse every key.
+// The authenticated `Principal` (or `User`) is then available to the handlers as an extractor.
+//
+// Tokens and keys are looked up by their SHA-256 hash, so the time a lookup takes tells
+// nothing about the token itself. Addresses sending too many invalid ones are locked out:
+// every token they send is refused, valid or not, until the lockout expires.
+#[derive(Clone, Copy)]
+pub struct RequireRole {
+    role: Role,
+    scope: Option<Scope>,
+}
+
+impl RequireRole {
+    pub fn new(role: Role) -> Self {
+        Self { role, scope: None }
+    }
+
+    /// Also lets in API keys holding `scope`.
+    pub fn or_scope(mut self, scope: Scope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+}
 
 // Middleware factory is `Transform` trait
 // `S` - type of the next service
 // `B` - type of response's body
-impl<S, B> Transform<S, ServiceRequest> for AdminAuth
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
     type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
     type InitError = ();
-    type Transform = AdminAuthMiddleware<S>;
+    type Transform = RequireRoleMiddleware<S>;
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        let admin_token = std::env::var("ADMIN_TOKEN").unwrap_or_else(|_| "default_admin_token".to_string());
-        ready(Ok(AdminAuthMiddleware { service, admin_token }))
+        ready(Ok(RequireRoleMiddleware {
+            service: Rc::new(service),
+            rule: *self,
+        }))
     }
 }
 
-pub struct AdminAuthMiddleware<S> {
-    service: S,
-    admin_token: String,
+pub struct RequireRoleMiddleware<S> {
+    // Shared with the future of each call, which outlives the borrow of `self`
+    service: Rc<S>,
+    rule: RequireRole,
+}
+
+/// Looks the token up on the blocking thread pool.
+async fn authenticate(
+    pool: Option<web::Data<Pool>>,
+    token: String,
+) -> Result<Principal, ServiceError> {
+    let pool =
+        pool.ok_or_else(|| ServiceError::Internal("Database pool not configured".to_string()))?;
+    db::run(&pool, move |conn| {
+        if token.starts_with(KEY_PREFIX) {
+            api_keys::authenticate(conn, &token).map(Principal::ApiKey)
+        } else {
+            users::authenticate(conn, &token).map(Principal::User)
+        }
+    })
+    .await
+}
+
+impl RequireRole {
+    /// `None` when allowed, otherwise the `403` to send back.
+    fn forbid(&self, principal: &Principal) -> Option<HttpResponse> {
+        match principal {
+            Principal::User(user) if user.role.allows(self.role) => None,
+            Principal::ApiKey(api_key)
+                if self.scope.is_some_and(|scope| api_key.has_scope(scope)) =>
+            {
+                None
+            }
+            Principal::User(_) => Some(
+                Problem::new(StatusCode::FORBIDDEN, "forbidden")
+                    .detail(format!("This requires the {} role", self.role))
+                    .response(),
+            ),
+            // Tell integrations which scope they are missing
+            Principal::ApiKey(_) => {
+                let mut challenge =
+                    format!("Bearer realm=\"{}\", error=\"insufficient_scope\"", REALM);
+                if let Some(scope) = self.scope {
+                    challenge.push_str(&format!(", scope=\"{}\"", scope.as_str()));
+                }
+                Some(
+                    Problem::new(StatusCode::FORBIDDEN, "insufficient_scope")
+                        .detail("This API key is not allowed to do this")
+                        .header(header::WWW_AUTHENTICATE, challenge)
+                        .response(),
+                )
+            }
+        }
+    }
 }
 
-impl<S, B> Service<ServiceRequest> for AdminAuthMiddleware<S>
+impl<S, B> Service<ServiceRequest> for RequireRoleMiddleware<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -52,23 +183,69 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // Get the Authorization header
-        let auth_header = req
-            .headers()
-            .get("Authorization")
-            .and_then(|h| h.to_str().ok());
-        
-        // If the auth_token is different from the admin token,
-        if Some(self.admin_token.as_str()) != auth_header {
-            // Return a 403 Forbidden response
-            let res = actix_web::HttpResponse::Forbidden().finish();
-            return Box::pin(async { Ok(req.into_response(res.map_into_right_body())) });
+        let reject = |req: ServiceRequest, res: HttpResponse| -> Result<Self::Response, Error> {
+            Ok(req.into_response(res.map_into_right_body()))
+        };
+
+        let lockout = req.app_data::<web::Data<Lockout>>().cloned();
+        let ip = lockout
+            .as_ref()
+            .and_then(|lockout| lockout.client_ip(req.request()));
+        if let (Some(ip), Some(lockout)) = (ip, &lockout) {
+            if let Err(retry_after) = lockout.check(ip) {
+                return Box::pin(ready(reject(req, locked_out(retry_after))));
+            }
         }
+        // No credentials at all, not an attempt worth counting
+        let Some(token) = bearer_token(req.headers()).map(str::to_string) else {
+            return Box::pin(ready(reject(req, unauthorized(None))));
+        };
 
-        self.service
-            .call(req)
-            .map_ok(ServiceResponse::map_into_left_body)
-            .boxed_local()
+        let service = Rc::clone(&self.service);
+        let rule = self.rule;
+        let pool = req.app_data::<web::Data<Pool>>().cloned();
+        Box::pin(async move {
+            let principal = match authenticate(pool, token).await {
+                Ok(principal) => principal,
+                Err(ServiceError::Unauthorized) => {
+                    log::warn!(
+                        target: "audit",
+                        "Invalid token for {} {} from {}",
+                        req.method(),
+                        req.path(),
+                        ip.map_or("unknown address".to_string(), |ip| ip.to_string())
+                    );
+                    if let (Some(ip), Some(lockout)) = (ip, &lockout) {
+                        if lockout.record_failure(ip) {
+                            log::warn!(
+                                target: "audit",
+                                "Locked out {} after repeated failures",
+                                ip
+                            );
+                        }
+                    }
+                    return reject(req, unauthorized(Some("invalid_token")));
+                }
+                Err(e) => return reject(req, e.error_response()),
+            };
+
+            // Authenticated but not allowed to do this
+            if let Some(res) = rule.forbid(&principal) {
+                log::warn!(
+                    target: "audit",
+                    "Denied {} {} to {}",
+                    req.method(),
+                    req.path(),
+                    principal.actor()
+                );
+                return reject(req, res);
+            }
+
+            req.extensions_mut().insert(principal);
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
     }
 }
-
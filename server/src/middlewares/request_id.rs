@@ -0,0 +1,100 @@
+use std::future::{ready, Ready};
+
+use actix_web::{
+    body::{BodySize, EitherBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{self, HeaderName, HeaderValue},
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+use uuid::Uuid;
+
+use crate::utils::problem::{Problem, REQUEST_ID};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest `X-Request-Id` accepted from a client or proxy
+const MAX_LENGTH: usize = 64;
+
+/// Keeps the `X-Request-Id` of the incoming request, so ids can be followed across a
+/// proxy, or generates one. The id is echoed in the response, set for the problem
+/// bodies built while handling the request, and errors sent without a body (such as
+/// a `405` from the router) are given a problem body.
+pub struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware { service }))
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: S,
+}
+
+fn incoming_id(req: &ServiceRequest) -> Option<String> {
+    let id = req.headers().get(&REQUEST_ID_HEADER)?.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then(|| id.to_string())
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = incoming_id(&req).unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+        // Inner middlewares may answer before returning their future
+        let fut = REQUEST_ID.sync_scope(id.clone(), || self.service.call(req));
+        Box::pin(REQUEST_ID.scope(id.clone(), async move {
+            let res = fut.await?;
+            let status = res.status();
+            let empty = matches!(
+                res.response().body().size(),
+                BodySize::None | BodySize::Sized(0)
+            );
+
+            let mut res = if empty && (status.is_client_error() || status.is_server_error()) {
+                let (req, res) = res.into_parts();
+                let mut problem = Problem::from_status(status).response();
+                // Keep what the empty response told the client, e.g. `Allow` or `WWW-Authenticate`
+                for (name, value) in res.headers() {
+                    if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+                        problem.headers_mut().insert(name.clone(), value.clone());
+                    }
+                }
+                ServiceResponse::new(req, problem).map_into_right_body()
+            } else {
+                res.map_into_left_body()
+            };
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(res)
+        }))
+    }
+}
@@ -0,0 +1,60 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use serde::Deserialize;
+
+use super::models::OutboxStatus;
+use super::service;
+use crate::tickets::ServiceError;
+use crate::utils::db;
+use crate::utils::pagination::PaginationQuery;
+use crate::Pool;
+
+pub async fn get_stats(db: web::Data<Pool>) -> impl Responder {
+    match db::run(&db, move |conn| service::get_stats(conn)).await {
+        Ok(stats) => HttpResponse::Ok().json(stats),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutboxQuery {
+    /// Defaults to the emails that were given up on
+    status: Option<OutboxStatus>,
+}
+
+pub async fn get_emails(
+    db: web::Data<Pool>,
+    query: web::Query<PaginationQuery>,
+    outbox: web::Query<OutboxQuery>,
+) -> impl Responder {
+    let query = query.into_inner();
+
+    if let Err(e) = query.validate() {
+        return ServiceError::Invalid(format!("Invalid pagination parameters: {}", e))
+            .error_response();
+    }
+    if let Ok(Some(_)) = query.cursor() {
+        return ServiceError::Invalid(
+            "Invalid pagination parameters: Cursors are not supported for the outbox".to_string(),
+        )
+        .error_response();
+    }
+
+    let status = outbox.status.unwrap_or(OutboxStatus::Dead);
+    match db::run(&db, move |conn| {
+        service::get_emails(conn, status, query.page(), query.limit())
+    })
+    .await
+    {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn retry_email(db: web::Data<Pool>, path: web::Path<i64>) -> impl Responder {
+    let id = path.into_inner();
+
+    match db::run(&db, move |conn| service::retry_email(conn, id)).await {
+        Ok(email) => HttpResponse::Ok().json(email),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,6 @@
+pub mod handlers;
+pub mod models;
+mod repository;
+pub mod routes;
+pub(crate) mod service;
+pub mod worker;
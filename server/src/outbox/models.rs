@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::mailer::{Email, Mailbox};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxStatus {
+    /// Waiting for its first or next delivery attempt
+    Pending,
+    Sent,
+    /// Gave up after too many failed attempts
+    Dead,
+}
+
+impl OutboxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Sent => "sent",
+            OutboxStatus::Dead => "dead",
+        }
+    }
+}
+
+impl std::str::FromStr for OutboxStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(OutboxStatus::Pending),
+            "sent" => Ok(OutboxStatus::Sent),
+            "dead" => Ok(OutboxStatus::Dead),
+            other => Err(format!("Unknown outbox status: {}", other)),
+        }
+    }
+}
+
+impl ToSql for OutboxStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for OutboxStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboxEmail {
+    pub id: i64,
+    pub recipient_name: String,
+    pub recipient_email: String,
+    pub subject: String,
+    #[serde(skip_serializing)]
+    pub html: String,
+    #[serde(skip_serializing)]
+    pub text: Option<String>,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutboxStats {
+    pub pending: i64,
+    pub sent: i64,
+    pub dead: i64,
+    /// Pending emails that already failed at least once
+    pub retrying: i64,
+    pub oldest_pending_at: Option<DateTime<Utc>>,
+}
+
+impl OutboxEmail {
+    pub fn email(&self) -> Email {
+        Email {
+            to: Mailbox {
+                name: self.recipient_name.clone(),
+                email: self.recipient_email.clone(),
+            },
+            subject: self.subject.clone(),
+            html: self.html.clone(),
+            text: self.text.clone(),
+        }
+    }
+}
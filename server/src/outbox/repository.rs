@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use super::models::{OutboxEmail, OutboxStatus};
+use crate::utils::mailer::Email;
+
+fn email_from_row(row: &rusqlite::Row) -> Result<OutboxEmail, rusqlite::Error> {
+    Ok(OutboxEmail {
+        id: row.get("id")?,
+        recipient_name: row.get("recipient_name")?,
+        recipient_email: row.get("recipient_email")?,
+        subject: row.get("subject")?,
+        html: row.get("html")?,
+        text: row.get("text")?,
+        status: row.get("status")?,
+        attempts: row.get("attempts")?,
+        next_attempt_at: row.get("next_attempt_at")?,
+        last_error: row.get("last_error")?,
+        created_at: row.get("created_at")?,
+        sent_at: row.get("sent_at")?,
+    })
+}
+
+pub fn create(
+    conn: &Connection,
+    email: &Email,
+    now: DateTime<Utc>,
+) -> Result<i64, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO email_outbox (recipient_name, recipient_email, subject, html, text, status, next_attempt_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7);",
+    )?;
+    stmt.execute(params![
+        email.to.name,
+        email.to.email,
+        email.subject,
+        email.html,
+        email.text,
+        OutboxStatus::Pending,
+        now,
+    ])?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_by_id(conn: &Connection, id: i64) -> Result<OutboxEmail, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM email_outbox WHERE id = ?1;")?;
+    stmt.query_row([id], email_from_row)
+}
+
+pub fn get_due(
+    conn: &Connection,
+    now: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<OutboxEmail>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM email_outbox WHERE status = ?1 AND next_attempt_at <= ?2 ORDER BY id LIMIT ?3;",
+    )?;
+    stmt.query_map(params![OutboxStatus::Pending, now, limit], email_from_row)
+        .and_then(Iterator::collect)
+}
+
+pub fn get_by_status(
+    conn: &Connection,
+    status: OutboxStatus,
+    page: u32,
+    limit: u32,
+) -> Result<Vec<OutboxEmail>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM email_outbox WHERE status = ?1 ORDER BY id DESC LIMIT ?2 OFFSET ?3;",
+    )?;
+    stmt.query_map(params![status, limit, (page - 1) * limit], email_from_row)
+        .and_then(Iterator::collect)
+}
+
+pub fn get_count_by_status(
+    conn: &Connection,
+    status: OutboxStatus,
+) -> Result<i64, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT COUNT(*) FROM email_outbox WHERE status = ?1;")?;
+    stmt.query_row([status], |row| row.get(0))
+}
+
+pub fn get_retrying_count(conn: &Connection) -> Result<i64, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT COUNT(*) FROM email_outbox WHERE status = ?1 AND attempts > 0;")?;
+    stmt.query_row([OutboxStatus::Pending], |row| row.get(0))
+}
+
+pub fn get_oldest_pending_at(conn: &Connection) -> Result<Option<DateTime<Utc>>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT MIN(created_at) FROM email_outbox WHERE status = ?1;")?;
+    stmt.query_row([OutboxStatus::Pending], |row| row.get(0))
+}
+
+/// Also clears the body, it holds links that must not outlive the email in the database.
+pub fn mark_sent(conn: &Connection, id: i64, now: DateTime<Utc>) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "UPDATE email_outbox SET status = ?1, attempts = attempts + 1, last_error = NULL, sent_at = ?2, html = '', text = NULL WHERE id = ?3;",
+    )?;
+    stmt.execute(params![OutboxStatus::Sent, now, id])?;
+    Ok(())
+}
+
+/// Records a failed attempt, the email stays pending until `next_attempt_at` unless `status` is dead.
+pub fn mark_failed(
+    conn: &Connection,
+    id: i64,
+    status: OutboxStatus,
+    next_attempt_at: DateTime<Utc>,
+    error: &str,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "UPDATE email_outbox SET status = ?1, attempts = attempts + 1, next_attempt_at = ?2, last_error = ?3 WHERE id = ?4;",
+    )?;
+    stmt.execute(params![status, next_attempt_at, error, id])?;
+    Ok(())
+}
+
+/// Puts the email back in the queue for an immediate attempt with a fresh attempt count.
+pub fn requeue(conn: &Connection, id: i64, now: DateTime<Utc>) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "UPDATE email_outbox SET status = ?1, attempts = 0, next_attempt_at = ?2 WHERE id = ?3;",
+    )?;
+    stmt.execute(params![OutboxStatus::Pending, now, id])?;
+    Ok(())
+}
+
+/// Deletes the dead emails queued before `before`, returns how many were deleted.
+pub fn delete_dead_before(
+    conn: &Connection,
+    before: DateTime<Utc>,
+) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM email_outbox WHERE status = ?1 AND created_at < ?2;",
+        params![OutboxStatus::Dead, before],
+    )
+}
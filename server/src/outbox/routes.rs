@@ -0,0 +1,21 @@
+use super::handlers;
+use crate::users::models::Role;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/outbox")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Admin))
+            .route(web::get().to(handlers::get_emails)),
+    );
+    cfg.service(
+        web::resource("/outbox/stats")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Admin))
+            .route(web::get().to(handlers::get_stats)),
+    );
+    cfg.service(
+        web::resource("/outbox/{id}/retry")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Admin))
+            .route(web::post().to(handlers::retry_email)),
+    );
+}
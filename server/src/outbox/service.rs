@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use super::models::{OutboxEmail, OutboxStats, OutboxStatus};
+use super::repository;
+use crate::tickets::ServiceError;
+use crate::utils::mailer::Email;
+use crate::utils::pagination::PaginatedResponse;
+
+/// Queues an email for delivery by the outbox worker.
+///
+/// Call it with the transaction that performs the change the email is about,
+/// so the email is only sent if that change is committed.
+pub fn enqueue(
+    conn: &Connection,
+    email: &Email,
+    now: DateTime<Utc>,
+) -> Result<i64, rusqlite::Error> {
+    repository::create(conn, email, now)
+}
+
+pub fn get_stats(conn: &Connection) -> Result<OutboxStats, ServiceError> {
+    Ok(OutboxStats {
+        pending: repository::get_count_by_status(conn, OutboxStatus::Pending)?,
+        sent: repository::get_count_by_status(conn, OutboxStatus::Sent)?,
+        dead: repository::get_count_by_status(conn, OutboxStatus::Dead)?,
+        retrying: repository::get_retrying_count(conn)?,
+        oldest_pending_at: repository::get_oldest_pending_at(conn)?,
+    })
+}
+
+pub fn get_emails(
+    conn: &Connection,
+    status: OutboxStatus,
+    page: u32,
+    limit: u32,
+) -> Result<PaginatedResponse<OutboxEmail>, ServiceError> {
+    let emails = repository::get_by_status(conn, status, page, limit)?;
+    let total = repository::get_count_by_status(conn, status)?;
+    Ok(PaginatedResponse::new(emails, page, limit, total as u32))
+}
+
+/// Puts a dead or pending email back at the front of the queue.
+pub fn retry_email(conn: &Connection, id: i64) -> Result<OutboxEmail, ServiceError> {
+    let email = repository::get_by_id(conn, id)?;
+    if email.status == OutboxStatus::Sent {
+        return Err(ServiceError::Conflict(
+            "Email has already been sent".to_string(),
+        ));
+    }
+    repository::requeue(conn, id, chrono::Utc::now())?;
+    repository::get_by_id(conn, id).map_err(ServiceError::from)
+}
@@ -0,0 +1,278 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use super::models::OutboxStatus;
+use super::repository;
+use crate::utils::db;
+use crate::utils::mailer::Mailer;
+use crate::Pool;
+
+/// Emails fetched from the queue on each poll
+const BATCH_SIZE: u32 = 20;
+/// Upper bound of the delay between two attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+/// How often dead emails past their retention are deleted
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct OutboxSettings {
+    /// Attempts before an email is marked dead
+    pub max_attempts: u32,
+    pub poll_interval: Duration,
+    /// Delay before the first retry, doubled after every failure
+    pub retry_base: Duration,
+    /// How long dead emails are kept so they can be retried, their bodies hold working links
+    pub retention: Duration,
+}
+
+impl OutboxSettings {
+    /// Delay before the next attempt of an email that failed `attempts` times.
+    pub fn retry_delay(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.retry_base
+            .checked_mul(factor)
+            .unwrap_or(MAX_RETRY_DELAY)
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Sends the emails that are due, the connection is released while talking to the mail backend.
+async fn deliver_due(
+    pool: &Pool,
+    mailer: &dyn Mailer,
+    settings: &OutboxSettings,
+) -> Result<(), String> {
+    let due = db::run(pool, |conn| {
+        Ok(repository::get_due(conn, Utc::now(), BATCH_SIZE)?)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for email in due {
+        let result = mailer.send(&email.email()).await;
+        let now = Utc::now();
+        let id = email.id;
+
+        match result {
+            Ok(()) => db::run(pool, move |conn| Ok(repository::mark_sent(conn, id, now)?)).await,
+            Err(e) => {
+                let attempts = email.attempts + 1;
+                let status = if attempts >= settings.max_attempts {
+                    log::error!(
+                        "Giving up on email {} to {} after {} attempts: {}",
+                        email.id,
+                        email.recipient_email,
+                        attempts,
+                        e
+                    );
+                    OutboxStatus::Dead
+                } else {
+                    log::warn!(
+                        "Failed to send email {} to {} (attempt {}): {}",
+                        email.id,
+                        email.recipient_email,
+                        attempts,
+                        e
+                    );
+                    OutboxStatus::Pending
+                };
+                let next_attempt_at = now
+                    + chrono::Duration::from_std(settings.retry_delay(attempts))
+                        .unwrap_or_else(|_| chrono::Duration::hours(1));
+                let error = e.to_string();
+                db::run(pool, move |conn| {
+                    Ok(repository::mark_failed(
+                        conn,
+                        id,
+                        status,
+                        next_attempt_at,
+                        &error,
+                    )?)
+                })
+                .await
+            }
+        }
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Deletes the dead emails older than the retention, sent ones are already emptied.
+async fn purge_dead(pool: &Pool, settings: &OutboxSettings) -> Result<(), String> {
+    let Some(before) = chrono::Duration::from_std(settings.retention)
+        .ok()
+        .and_then(|retention| Utc::now().checked_sub_signed(retention))
+    else {
+        return Ok(());
+    };
+    let deleted = db::run(pool, move |conn| {
+        Ok(repository::delete_dead_before(conn, before)?)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    if deleted > 0 {
+        log::info!("Deleted {} dead email(s) past their retention", deleted);
+    }
+    Ok(())
+}
+
+/// Starts the background task draining the outbox for the lifetime of the server.
+pub fn spawn(pool: Pool, mailer: Arc<dyn Mailer>, settings: OutboxSettings) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(settings.poll_interval);
+        let mut last_purge: Option<Instant> = None;
+        loop {
+            interval.tick().await;
+            if let Err(e) = deliver_due(&pool, mailer.as_ref(), &settings).await {
+                log::error!("Failed to process the email outbox: {}", e);
+            }
+            if last_purge.is_none_or(|at| at.elapsed() >= PURGE_INTERVAL) {
+                last_purge = Some(Instant::now());
+                if let Err(e) = purge_dead(&pool, &settings).await {
+                    log::error!("Failed to purge the email outbox: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbox::service as outbox;
+    use crate::utils::mailer::memory::MemoryMailer;
+    use crate::utils::mailer::{Email, MailError, Mailbox};
+    use futures_util::future::BoxFuture;
+
+    fn settings() -> OutboxSettings {
+        OutboxSettings {
+            max_attempts: 2,
+            poll_interval: Duration::from_secs(5),
+            retry_base: Duration::from_secs(30),
+            retention: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+
+    fn email(to: &str) -> Email {
+        Email {
+            to: Mailbox {
+                name: "Requester".to_string(),
+                email: to.to_string(),
+            },
+            subject: "Ticket received".to_string(),
+            html: "<p>Hello</p>".to_string(),
+            text: Some("Hello".to_string()),
+        }
+    }
+
+    struct FailingMailer;
+
+    impl Mailer for FailingMailer {
+        fn send<'a>(&'a self, _email: &'a Email) -> BoxFuture<'a, Result<(), MailError>> {
+            Box::pin(async { Err(MailError::Delivery("connection refused".to_string())) })
+        }
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let settings = settings();
+        let delays: Vec<u64> = (1..=9)
+            .map(|attempts| settings.retry_delay(attempts).as_secs())
+            .collect();
+        assert_eq!(delays, [30, 60, 120, 240, 480, 960, 1920, 3600, 3600]);
+        // Never overflows, however many attempts
+        assert_eq!(settings.retry_delay(u32::MAX), MAX_RETRY_DELAY);
+        assert_eq!(settings.retry_delay(0), settings.retry_base);
+        let huge = OutboxSettings {
+            retry_base: Duration::from_secs(u64::MAX),
+            ..settings
+        };
+        assert_eq!(huge.retry_delay(3), MAX_RETRY_DELAY);
+    }
+
+    #[actix_web::test]
+    async fn delivers_queued_emails_once() {
+        let pool = db::test_pool();
+        outbox::enqueue(&pool.get().unwrap(), &email("a@example.org"), Utc::now()).unwrap();
+        let mailer = MemoryMailer::new();
+
+        deliver_due(&pool, &mailer, &settings()).await.unwrap();
+        deliver_due(&pool, &mailer, &settings()).await.unwrap();
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to.email, "a@example.org");
+        assert_eq!(sent[0].text.as_deref(), Some("Hello"));
+        let conn = pool.get().unwrap();
+        let stored = repository::get_by_id(&conn, 1).unwrap();
+        assert_eq!(stored.status, OutboxStatus::Sent);
+        // The body held the links, it is not kept once sent
+        assert_eq!(stored.html, "");
+        assert_eq!(stored.text, None);
+    }
+
+    #[actix_web::test]
+    async fn purges_dead_emails_past_their_retention() {
+        let pool = db::test_pool();
+        let conn = pool.get().unwrap();
+        let old = Utc::now() - chrono::Duration::days(8);
+        let recent = Utc::now() - chrono::Duration::days(6);
+        for (queued_at, status) in [
+            (old, OutboxStatus::Dead),
+            (recent, OutboxStatus::Dead),
+            (old, OutboxStatus::Pending),
+        ] {
+            let id = outbox::enqueue(&conn, &email("a@example.org"), queued_at).unwrap();
+            repository::mark_failed(&conn, id, status, queued_at, "refused").unwrap();
+        }
+        drop(conn);
+
+        purge_dead(&pool, &settings()).await.unwrap();
+
+        let conn = pool.get().unwrap();
+        assert!(repository::get_by_id(&conn, 1).is_err());
+        assert!(repository::get_by_id(&conn, 2).is_ok());
+        assert!(repository::get_by_id(&conn, 3).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn failed_emails_are_retried_later_then_given_up() {
+        let pool = db::test_pool();
+        let id =
+            outbox::enqueue(&pool.get().unwrap(), &email("a@example.org"), Utc::now()).unwrap();
+
+        deliver_due(&pool, &FailingMailer, &settings())
+            .await
+            .unwrap();
+        let email = repository::get_by_id(&pool.get().unwrap(), id).unwrap();
+        assert_eq!(email.status, OutboxStatus::Pending);
+        assert_eq!(email.attempts, 1);
+        assert!(email.next_attempt_at > Utc::now() + chrono::Duration::seconds(20));
+        assert_eq!(
+            email.last_error.as_deref(),
+            Some("Delivery failed: connection refused")
+        );
+
+        // Not due yet
+        let mailer = MemoryMailer::new();
+        deliver_due(&pool, &mailer, &settings()).await.unwrap();
+        assert!(mailer.sent().is_empty());
+
+        // Once due, the second failure is the last one
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE email_outbox SET next_attempt_at = ?1;",
+                [Utc::now()],
+            )
+            .unwrap();
+        deliver_due(&pool, &FailingMailer, &settings())
+            .await
+            .unwrap();
+        let email = repository::get_by_id(&pool.get().unwrap(), id).unwrap();
+        assert_eq!(email.status, OutboxStatus::Dead);
+        assert_eq!(email.attempts, 2);
+    }
+}
@@ -0,0 +1,99 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::Deserialize;
+
+use super::service;
+use crate::config::Config;
+use crate::middlewares::auth::{bearer_token, unauthorized};
+use crate::tickets::ServiceError;
+use crate::utils::db;
+use crate::utils::notifications::Notifications;
+use crate::utils::pagination::PaginationQuery;
+use crate::Pool;
+
+#[derive(Debug, Deserialize)]
+pub struct PortalLogin {
+    email: String,
+}
+
+pub async fn post_login(
+    db: web::Data<Pool>,
+    config: web::Data<Config>,
+    notifications: web::Data<Notifications>,
+    body: web::Json<PortalLogin>,
+) -> impl Responder {
+    match db::run(&db, move |conn| {
+        service::request_login(conn, &notifications, &config.portal, &body.email)
+    })
+    .await
+    {
+        // The same answer whether the address has tickets or not
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PortalSessionRequest {
+    token: String,
+}
+
+pub async fn post_session(
+    db: web::Data<Pool>,
+    config: web::Data<Config>,
+    body: web::Json<PortalSessionRequest>,
+) -> impl Responder {
+    match db::run(&db, move |conn| {
+        service::create_session(conn, &config.portal, &body.token)
+    })
+    .await
+    {
+        Ok(session) => HttpResponse::Ok().json(session),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn post_logout(db: web::Data<Pool>, req: HttpRequest) -> impl Responder {
+    let Some(token) = bearer_token(req.headers()).map(str::to_string) else {
+        return unauthorized(None);
+    };
+
+    match db::run(&db, move |conn| service::logout(conn, &token)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_tickets(
+    db: web::Data<Pool>,
+    req: HttpRequest,
+    query: web::Query<PaginationQuery>,
+) -> impl Responder {
+    let Some(token) = bearer_token(req.headers()).map(str::to_string) else {
+        return unauthorized(None);
+    };
+    let email = match db::run(&db, move |conn| service::authenticate(conn, &token)).await {
+        Ok(email) => email,
+        Err(ServiceError::Unauthorized) => return unauthorized(Some("invalid_token")),
+        // Not a reason to drop the session, e.g. the database is busy
+        Err(e) => return e.error_response(),
+    };
+
+    if let Err(e) = query.validate() {
+        return ServiceError::Invalid(format!("Invalid pagination parameters: {}", e))
+            .error_response();
+    }
+    if let Ok(Some(_)) = query.cursor() {
+        return ServiceError::Invalid(
+            "Invalid pagination parameters: Cursors are not supported for the portal".to_string(),
+        )
+        .error_response();
+    }
+    match db::run(&db, move |conn| {
+        service::get_tickets(conn, &email, query.page(), query.limit())
+    })
+    .await
+    {
+        Ok(tickets) => HttpResponse::Ok().json(tickets),
+        Err(e) => e.error_response(),
+    }
+}
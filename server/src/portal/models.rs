@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Clone)]
+pub struct PortalSettings {
+    /// Lifetime of the one-time links emailed to requesters
+    pub link_ttl: Duration,
+    /// Lifetime of the session a link is exchanged for
+    pub session_ttl: Duration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PortalSession {
+    /// Sent back as `Authorization: Bearer <token>`
+    pub token: String,
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+}
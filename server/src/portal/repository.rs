@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+pub fn create_login(
+    conn: &Connection,
+    token_hash: &str,
+    email: &str,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO portal_logins (token_hash, email, created_at, expires_at) VALUES (?1, ?2, ?3, ?4);",
+        params![token_hash, email, created_at, expires_at],
+    )?;
+    Ok(())
+}
+
+/// Unexpired links sent to an address.
+pub fn get_pending_login_count(
+    conn: &Connection,
+    email: &str,
+    now: DateTime<Utc>,
+) -> Result<i64, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT COUNT(*) FROM portal_logins WHERE email = ?1 AND expires_at > ?2;")?;
+    stmt.query_row(params![email, now], |row| row.get(0))
+}
+
+/// The email of an unexpired link.
+pub fn get_login_email(
+    conn: &Connection,
+    token_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<String, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT email FROM portal_logins WHERE token_hash = ?1 AND expires_at > ?2;")?;
+    stmt.query_row(params![token_hash, now], |row| row.get(0))
+}
+
+pub fn delete_login(conn: &Connection, token_hash: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM portal_logins WHERE token_hash = ?1;",
+        [token_hash],
+    )?;
+    Ok(())
+}
+
+pub fn delete_expired_logins(conn: &Connection, now: DateTime<Utc>) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM portal_logins WHERE expires_at <= ?1;", [now])?;
+    Ok(())
+}
+
+pub fn create_session(
+    conn: &Connection,
+    token_hash: &str,
+    email: &str,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO portal_sessions (token_hash, email, created_at, expires_at) VALUES (?1, ?2, ?3, ?4);",
+        params![token_hash, email, created_at, expires_at],
+    )?;
+    Ok(())
+}
+
+/// The email of an unexpired session.
+pub fn get_session_email(
+    conn: &Connection,
+    token_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<String, rusqlite::Error> {
+    let mut stmt = conn
+        .prepare("SELECT email FROM portal_sessions WHERE token_hash = ?1 AND expires_at > ?2;")?;
+    stmt.query_row(params![token_hash, now], |row| row.get(0))
+}
+
+pub fn delete_session(conn: &Connection, token_hash: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM portal_sessions WHERE token_hash = ?1;",
+        [token_hash],
+    )?;
+    Ok(())
+}
+
+pub fn delete_expired_sessions(
+    conn: &Connection,
+    now: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM portal_sessions WHERE expires_at <= ?1;", [now])?;
+    Ok(())
+}
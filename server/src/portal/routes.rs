@@ -0,0 +1,11 @@
+use super::handlers;
+use actix_web::web;
+
+// Requesters authenticate with the session their emailed link was exchanged for,
+// staff credentials are not accepted here.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/portal/login").route(web::post().to(handlers::post_login)));
+    cfg.service(web::resource("/portal/session").route(web::post().to(handlers::post_session)));
+    cfg.service(web::resource("/portal/logout").route(web::post().to(handlers::post_logout)));
+    cfg.service(web::resource("/portal/tickets").route(web::get().to(handlers::get_tickets)));
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+
+use super::models::{PortalSession, PortalSettings};
+use super::repository;
+use crate::messages::repository as messages_repository;
+use crate::outbox::service as outbox;
+use crate::requester::models::RequesterTicket;
+use crate::tickets::models::{TicketFilter, TicketSort};
+use crate::tickets::{repository as tickets_repository, ServiceError};
+use crate::users::service::{generate_token, hash_token};
+use crate::utils::mailer::Mailbox;
+use crate::utils::notifications::Notifications;
+use crate::utils::pagination::PaginatedResponse;
+use crate::utils::validation::{ValidationSettings, Validator};
+
+/// Unused links an address may have at once, so the portal cannot be used to flood a mailbox
+const MAX_PENDING_LOGINS: i64 = 3;
+
+/// TTLs are bounded by the configuration, the fallback only avoids a panic.
+fn expires_at(now: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .unwrap_or(now)
+}
+
+fn tickets_of(email: &str) -> TicketFilter {
+    TicketFilter {
+        email: Some(email.to_string()),
+        ..TicketFilter::default()
+    }
+}
+
+/// Emails a one-time portal link to `email` if it has tickets. Nothing tells the caller
+/// whether it did, so the portal cannot be used to find out who has tickets.
+pub fn request_login(
+    conn: &Connection,
+    notifications: &Notifications,
+    settings: &PortalSettings,
+    email: &str,
+) -> Result<(), ServiceError> {
+    // Syntax only, tickets submitted before a domain was blocked must stay reachable
+    let mut validator = Validator::new();
+    let email = validator.email("email", email, &ValidationSettings::default());
+    validator.finish()?;
+    let email = email.as_str();
+    // Address the email like the latest ticket was
+    let Some(latest) =
+        tickets_repository::get_all(conn, &tickets_of(email), &TicketSort::default(), 1, 1)?.pop()
+    else {
+        return Ok(());
+    };
+
+    let now = chrono::Utc::now();
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    repository::delete_expired_logins(&tx, now)?;
+    if repository::get_pending_login_count(&tx, &latest.email, now)? >= MAX_PENDING_LOGINS {
+        log::warn!(target: "audit", "Too many pending portal links for {}", latest.email);
+        return Ok(());
+    }
+    let token = generate_token();
+    repository::create_login(
+        &tx,
+        &hash_token(&token),
+        &latest.email,
+        now,
+        expires_at(now, settings.link_ttl),
+    )?;
+    let to = Mailbox {
+        name: latest.name,
+        email: latest.email,
+    };
+    outbox::enqueue(
+        &tx,
+        &notifications.portal_login_email(to, &token, settings.link_ttl)?,
+        now,
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Exchanges a link for a session, the link cannot be used again.
+pub fn create_session(
+    conn: &Connection,
+    settings: &PortalSettings,
+    login_token: &str,
+) -> Result<PortalSession, ServiceError> {
+    let now = chrono::Utc::now();
+    let login_hash = hash_token(login_token);
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let email = match repository::get_login_email(&tx, &login_hash, now) {
+        Ok(email) => email,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(ServiceError::Unauthorized),
+        Err(e) => return Err(e.into()),
+    };
+    repository::delete_login(&tx, &login_hash)?;
+    repository::delete_expired_sessions(&tx, now)?;
+    let token = generate_token();
+    let expires_at = expires_at(now, settings.session_ttl);
+    repository::create_session(&tx, &hash_token(&token), &email, now, expires_at)?;
+    tx.commit()?;
+
+    Ok(PortalSession {
+        token,
+        email,
+        expires_at,
+    })
+}
+
+pub fn logout(conn: &Connection, token: &str) -> Result<(), ServiceError> {
+    repository::delete_session(conn, &hash_token(token)).map_err(ServiceError::from)
+}
+
+/// Resolves a portal session to its email, `Unauthorized` if it is unknown or expired.
+pub fn authenticate(conn: &Connection, token: &str) -> Result<String, ServiceError> {
+    match repository::get_session_email(conn, &hash_token(token), chrono::Utc::now()) {
+        Ok(email) => Ok(email),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(ServiceError::Unauthorized),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Every ticket issued with `email`, newest first, as the requester sees them.
+pub fn get_tickets(
+    conn: &Connection,
+    email: &str,
+    page: u32,
+    limit: u32,
+) -> Result<PaginatedResponse<RequesterTicket>, ServiceError> {
+    let filter = tickets_of(email);
+    let tickets = tickets_repository::get_all(conn, &filter, &TicketSort::default(), page, limit)?;
+    let total = tickets_repository::get_count(conn, &filter)? as u32;
+
+    let tickets = tickets
+        .into_iter()
+        .map(|ticket| {
+            let messages = messages_repository::get_by_ticket(conn, &ticket.uuid)?;
+            Ok(RequesterTicket::new(ticket, messages))
+        })
+        .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+
+    Ok(PaginatedResponse::new(tickets, page, limit, total))
+}
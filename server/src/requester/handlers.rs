@@ -0,0 +1,87 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::service;
+use crate::config::Config;
+use crate::users::models::Principal;
+use crate::utils::db;
+use crate::utils::notifications::Notifications;
+use crate::Pool;
+
+pub async fn get_ticket(db: web::Data<Pool>, path: web::Path<String>) -> impl Responder {
+    let token = path.into_inner();
+
+    match db::run(&db, move |conn| service::get_ticket(conn, &token)).await {
+        Ok(ticket) => HttpResponse::Ok().json(ticket),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostReply {
+    body: String,
+}
+
+pub async fn post_reply(
+    db: web::Data<Pool>,
+    notifications: web::Data<Notifications>,
+    path: web::Path<String>,
+    body: web::Json<PostReply>,
+) -> impl Responder {
+    let token = path.into_inner();
+    let body = body.into_inner();
+
+    match db::run(&db, move |conn| {
+        service::add_reply(conn, &notifications, &token, body.body)
+    })
+    .await
+    {
+        Ok(ticket) => HttpResponse::Created().json(ticket),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn post_reopen(
+    db: web::Data<Pool>,
+    config: web::Data<Config>,
+    notifications: web::Data<Notifications>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let token = path.into_inner();
+
+    match db::run(&db, move |conn| {
+        service::reopen(conn, &notifications, &token, config.reopen_window)
+    })
+    .await
+    {
+        Ok(ticket) => HttpResponse::Ok().json(ticket),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostAccessToken {
+    #[serde(default)]
+    notify: bool,
+}
+
+pub async fn post_access_token(
+    db: web::Data<Pool>,
+    notifications: web::Data<Notifications>,
+    principal: Principal,
+    path: web::Path<Uuid>,
+    body: Option<web::Json<PostAccessToken>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let notify = body.is_some_and(|body| body.notify);
+
+    match db::run(&db, move |conn| {
+        service::rotate_token(conn, &notifications, id, notify, &principal)
+    })
+    .await
+    {
+        Ok(link) => HttpResponse::Created().json(link),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::messages::models::{MessageKind, TicketMessage};
+use crate::tickets::models::{Ticket, TicketStatus};
+
+/// A message as the requester sees it, staff email addresses left out.
+#[derive(Debug, Serialize)]
+pub struct RequesterMessage {
+    pub kind: MessageKind,
+    pub author_name: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What the holder of a ticket link may see, the internal note and notes,
+/// the ticket id and the requester email are left out.
+#[derive(Debug, Serialize)]
+pub struct RequesterTicket {
+    pub number: u32,
+    pub reference: String,
+    pub name: String,
+    pub message: String,
+    pub status: TicketStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub messages: Vec<RequesterMessage>,
+}
+
+impl RequesterTicket {
+    pub fn new(ticket: Ticket, messages: Vec<TicketMessage>) -> Self {
+        Self {
+            number: ticket.number,
+            reference: ticket.reference,
+            name: ticket.name,
+            message: ticket.message,
+            status: ticket.status,
+            created_at: ticket.created_at,
+            updated_at: ticket.updated_at,
+            closed_at: ticket.closed_at,
+            messages: messages
+                .into_iter()
+                .filter(|message| message.kind != MessageKind::InternalNote)
+                .map(|message| RequesterMessage {
+                    kind: message.kind,
+                    author_name: message.author_name,
+                    body: message.body,
+                    created_at: message.created_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Returned when a ticket is submitted, the token gives the requester access to it later on.
+#[derive(Debug, Serialize)]
+pub struct CreatedTicket {
+    pub access_token: String,
+    #[serde(flatten)]
+    pub ticket: RequesterTicket,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccessLink {
+    pub token: String,
+    pub link: String,
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::tickets::repository::parse_uuid;
+
+pub fn create_token(
+    conn: &Connection,
+    token_hash: &str,
+    ticket_id: &Uuid,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO ticket_access_tokens (ticket_uuid, token_hash, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4);",
+        params![ticket_id.to_string(), token_hash, created_at, expires_at],
+    )?;
+    Ok(())
+}
+
+/// The ticket an unexpired token gives access to.
+pub fn get_ticket_id_by_token(
+    conn: &Connection,
+    token_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<Uuid, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT ticket_uuid FROM ticket_access_tokens WHERE token_hash = ?1 AND expires_at > ?2;",
+    )?;
+    stmt.query_row(params![token_hash, now], |row| {
+        parse_uuid(&row.get::<_, String>(0)?)
+    })
+}
+
+pub fn delete_tokens_by_ticket(conn: &Connection, ticket_id: &Uuid) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM ticket_access_tokens WHERE ticket_uuid = ?1;",
+        [ticket_id.to_string()],
+    )?;
+    Ok(())
+}
+
+pub fn delete_expired_tokens(conn: &Connection, now: DateTime<Utc>) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM ticket_access_tokens WHERE expires_at <= ?1;",
+        [now],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use super::handlers;
+use crate::api_keys::models::Scope;
+use crate::middlewares::auth::RequireRole;
+use crate::users::models::Role;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    // The token is the credential, whoever holds the link sees the requester view
+    cfg.service(
+        web::resource("/requester/tickets/{token}").route(web::get().to(handlers::get_ticket)),
+    );
+    cfg.service(
+        web::resource("/requester/tickets/{token}/messages")
+            .route(web::post().to(handlers::post_reply)),
+    );
+    cfg.service(
+        web::resource("/requester/tickets/{token}/reopen")
+            .route(web::post().to(handlers::post_reopen)),
+    );
+    cfg.service(
+        web::resource("/tickets/{id}/access-token")
+            .wrap(RequireRole::new(Role::Agent).or_scope(Scope::TicketsWrite))
+            .route(web::post().to(handlers::post_access_token)),
+    );
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use uuid::Uuid;
+
+use super::models::{AccessLink, RequesterTicket};
+use super::repository;
+use crate::history::{service as history, ACTOR_REQUESTER};
+use crate::messages::models::{MessageKind, TicketMessage};
+use crate::messages::repository as messages_repository;
+use crate::outbox::service as outbox;
+use crate::tickets::models::{Ticket, TicketStatus};
+use crate::tickets::{repository as tickets_repository, ServiceError};
+use crate::users::models::Principal;
+use crate::users::service::{self as users, generate_token, hash_token};
+use crate::utils::notifications::Notifications;
+use crate::utils::validation::{self, Validator};
+
+/// TTLs are bounded by the configuration, the fallback only avoids a panic.
+fn expires_at(now: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .unwrap_or(now)
+}
+
+/// Creates a new link token for a ticket, previous ones stay valid until they expire.
+pub(crate) fn issue_token(
+    conn: &Connection,
+    ticket_id: Uuid,
+    now: DateTime<Utc>,
+    ttl: Duration,
+) -> Result<String, ServiceError> {
+    let token = generate_token();
+    repository::delete_expired_tokens(conn, now)?;
+    repository::create_token(
+        conn,
+        &hash_token(&token),
+        &ticket_id,
+        now,
+        expires_at(now, ttl),
+    )?;
+    Ok(token)
+}
+
+/// Revokes every link of a ticket.
+pub(crate) fn revoke_tokens(conn: &Connection, ticket_id: Uuid) -> Result<(), ServiceError> {
+    repository::delete_tokens_by_ticket(conn, &ticket_id).map_err(ServiceError::from)
+}
+
+/// Replaces every link of a ticket with a new one, e.g. after a link was forwarded
+/// to the wrong person. The new link is emailed to the requester if `notify` is set.
+pub fn rotate_token(
+    conn: &Connection,
+    notifications: &Notifications,
+    ticket_id: Uuid,
+    notify: bool,
+    principal: &Principal,
+) -> Result<AccessLink, ServiceError> {
+    let ticket = tickets_repository::get_by_id(conn, ticket_id)?;
+    let now = chrono::Utc::now();
+    let ttl = notifications.link_ttl();
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    revoke_tokens(&tx, ticket_id)?;
+    let token = issue_token(&tx, ticket_id, now, ttl)?;
+    history::record(
+        &tx,
+        ticket_id,
+        "access_token",
+        None,
+        Some("rotated".to_string()),
+        &principal.actor(),
+        now,
+    )?;
+    if notify {
+        outbox::enqueue(
+            &tx,
+            &notifications.notification_email(&ticket, &token)?,
+            now,
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(AccessLink {
+        link: notifications.ticket_link(&token),
+        token,
+        expires_at: expires_at(now, ttl),
+    })
+}
+
+fn get_ticket_by_token(conn: &Connection, token: &str) -> Result<Ticket, ServiceError> {
+    let ticket_id =
+        repository::get_ticket_id_by_token(conn, &hash_token(token), chrono::Utc::now())?;
+    tickets_repository::get_by_id(conn, ticket_id).map_err(ServiceError::from)
+}
+
+fn requester_view(conn: &Connection, ticket: Ticket) -> Result<RequesterTicket, ServiceError> {
+    let messages = messages_repository::get_by_ticket(conn, &ticket.uuid)?;
+    Ok(RequesterTicket::new(ticket, messages))
+}
+
+/// Emails every agent and admin about a reply, or a reopening when `message` is `None`.
+fn notify_staff(
+    conn: &Connection,
+    notifications: &Notifications,
+    ticket: &Ticket,
+    message: Option<&TicketMessage>,
+    now: DateTime<Utc>,
+) -> Result<(), ServiceError> {
+    for user in users::get_staff_to_notify(conn)? {
+        outbox::enqueue(
+            conn,
+            &notifications.staff_update_email(&user, ticket, message)?,
+            now,
+        )?;
+    }
+    Ok(())
+}
+
+/// The ticket a link token gives access to, `NotFound` if the token is unknown or expired.
+pub fn get_ticket(conn: &Connection, token: &str) -> Result<RequesterTicket, ServiceError> {
+    let ticket = get_ticket_by_token(conn, token)?;
+    requester_view(conn, ticket)
+}
+
+/// Adds a reply from the requester, a ticket waiting on them goes back to `open`.
+pub fn add_reply(
+    conn: &Connection,
+    notifications: &Notifications,
+    token: &str,
+    body: String,
+) -> Result<RequesterTicket, ServiceError> {
+    // Read under the write lock, so a concurrent change by staff cannot be overwritten
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let mut ticket = get_ticket_by_token(&tx, token)?;
+    let mut validator = Validator::new();
+    let body = validator.multiline("body", "Message", &body, validation::MESSAGE_MAX_LENGTH);
+    validator.finish()?;
+    if ticket.status == TicketStatus::Closed {
+        return Err(ServiceError::Conflict(
+            "The ticket is closed, reopen it to reply".to_string(),
+        ));
+    }
+    let before = ticket.clone();
+    let now = chrono::Utc::now();
+
+    let mut message = TicketMessage {
+        id: 0,
+        ticket_uuid: ticket.uuid,
+        kind: MessageKind::RequesterReply,
+        author_name: ticket.name.clone(),
+        author_email: Some(ticket.email.clone()),
+        body,
+        created_at: now,
+    };
+    if ticket.status == TicketStatus::Pending {
+        ticket.set_status(TicketStatus::Open, now);
+    }
+    ticket.updated_at = Some(now);
+
+    messages_repository::create(&tx, &mut message)?;
+    tickets_repository::update(&tx, &ticket.uuid, &ticket)?;
+    history::record_changes(&tx, &before, &ticket, ACTOR_REQUESTER, now)?;
+    history::record(
+        &tx,
+        ticket.uuid,
+        message.kind.as_str(),
+        None,
+        Some(message.id.to_string()),
+        ACTOR_REQUESTER,
+        now,
+    )?;
+    notify_staff(&tx, notifications, &ticket, Some(&message), now)?;
+    tx.commit()?;
+
+    requester_view(conn, ticket)
+}
+
+/// Reopens a ticket closed less than `window` ago.
+pub fn reopen(
+    conn: &Connection,
+    notifications: &Notifications,
+    token: &str,
+    window: Duration,
+) -> Result<RequesterTicket, ServiceError> {
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let mut ticket = get_ticket_by_token(&tx, token)?;
+    let now = chrono::Utc::now();
+    let Some(closed_at) = ticket
+        .closed_at
+        .filter(|_| ticket.status == TicketStatus::Closed)
+    else {
+        return Err(ServiceError::Conflict(
+            "Only closed tickets can be reopened".to_string(),
+        ));
+    };
+    if expires_at(closed_at, window) < now {
+        return Err(ServiceError::Conflict(
+            "The ticket was closed too long ago to be reopened, please issue a new one".to_string(),
+        ));
+    }
+    let before = ticket.clone();
+    ticket.set_status(TicketStatus::Open, now);
+    ticket.updated_at = Some(now);
+
+    tickets_repository::update(&tx, &ticket.uuid, &ticket)?;
+    history::record_changes(&tx, &before, &ticket, ACTOR_REQUESTER, now)?;
+    notify_staff(&tx, notifications, &ticket, None, now)?;
+    tx.commit()?;
+
+    requester_view(conn, ticket)
+}
@@ -1,21 +1,155 @@
 use actix_web::{web, HttpResponse, Responder, ResponseError};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use serde::Deserialize;
 use uuid::Uuid;
 
+use super::models::{DateRange, TicketFilter, TicketSort, TicketStatus};
+use super::numbering::NumberFormat;
 use super::service::{self, CreateTicketRequest, UpdateTicketRequest};
-use crate::utils::db::Connection;
+use super::ServiceError;
+use crate::config::Config;
+use crate::history::ACTOR_REQUESTER;
+use crate::requester::models::{CreatedTicket, RequesterTicket};
+use crate::users::models::{Principal, User};
+use crate::utils::db;
+use crate::utils::notifications::Notifications;
 use crate::utils::pagination::PaginationQuery;
 use crate::Pool;
 
-pub async fn get_all(db: web::Data<Pool>, query: web::Query<PaginationQuery>) -> impl Responder {
-    let conn: Connection = db.get().expect("Failed to get DB connection");
+#[derive(Debug, Deserialize)]
+pub struct TicketListQuery {
+    /// Full-text search over tickets and their conversation
+    q: Option<String>,
+    /// Comma separated list of statuses, e.g. `open,pending`
+    status: Option<String>,
+    email: Option<String>,
+    created_after: Option<String>,
+    created_before: Option<String>,
+    updated_after: Option<String>,
+    updated_before: Option<String>,
+    closed_after: Option<String>,
+    closed_before: Option<String>,
+    number_min: Option<u32>,
+    number_max: Option<u32>,
+    /// Field to sort by, prefixed with `-` for descending order
+    sort: Option<String>,
+}
+
+/// Accepts either an RFC 3339 timestamp or a plain `YYYY-MM-DD` date (midnight UTC).
+fn parse_date(name: &str, value: &Option<String>) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(Some(date.with_timezone(&Utc)));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| Some(date.and_time(NaiveTime::MIN).and_utc()))
+        .map_err(|_| format!("{} must be a date or an RFC 3339 timestamp", name))
+}
+
+impl TicketListQuery {
+    pub fn filter(&self) -> Result<TicketFilter, String> {
+        let statuses = match &self.status {
+            Some(statuses) => statuses
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<TicketStatus>, _>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(TicketFilter {
+            statuses,
+            email: self.email.clone(),
+            created: DateRange {
+                after: parse_date("created_after", &self.created_after)?,
+                before: parse_date("created_before", &self.created_before)?,
+            },
+            updated: DateRange {
+                after: parse_date("updated_after", &self.updated_after)?,
+                before: parse_date("updated_before", &self.updated_before)?,
+            },
+            closed: DateRange {
+                after: parse_date("closed_after", &self.closed_after)?,
+                before: parse_date("closed_before", &self.closed_before)?,
+            },
+            number_min: self.number_min,
+            number_max: self.number_max,
+        })
+    }
+
+    pub fn sort(&self) -> Result<Option<TicketSort>, String> {
+        self.sort.as_deref().map(str::parse).transpose()
+    }
+}
+
+pub async fn get_all(
+    db: web::Data<Pool>,
+    query: web::Query<PaginationQuery>,
+    list: web::Query<TicketListQuery>,
+) -> impl Responder {
     let query = query.into_inner();
 
     if let Err(e) = query.validate() {
-        return HttpResponse::BadRequest().body(format!("Invalid pagination parameters: {}", e));
+        return ServiceError::Invalid(format!("Invalid pagination parameters: {}", e))
+            .error_response();
+    }
+    let (filter, sort) = match list.filter().and_then(|f| Ok((f, list.sort()?))) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return ServiceError::Invalid(format!("Invalid filter parameters: {}", e))
+                .error_response()
+        }
+    };
+
+    if let Ok(Some(cursor)) = query.cursor() {
+        if list.q.is_some() || sort.is_some_and(|sort| sort != TicketSort::default()) {
+            return ServiceError::Invalid(
+                "Invalid pagination parameters: Cursors only support the default order".to_string(),
+            )
+            .error_response();
+        }
+        return match db::run(&db, move |conn| {
+            service::get_tickets_by_cursor(conn, &filter, &cursor, query.limit())
+        })
+        .await
+        {
+            Ok(response) => HttpResponse::Ok().json(response),
+            Err(e) => e.error_response(),
+        };
+    }
+
+    if let Some(text) = list.q.clone() {
+        return match db::run(&db, move |conn| {
+            service::search_tickets(
+                conn,
+                &text,
+                &filter,
+                sort.as_ref(),
+                query.page(),
+                query.limit(),
+            )
+        })
+        .await
+        {
+            Ok(response) => HttpResponse::Ok().json(response),
+            Err(e) => e.error_response(),
+        };
     }
 
-    match service::get_all_tickets(&conn, query.page(), query.limit()) {
+    match db::run(&db, move |conn| {
+        service::get_all_tickets(
+            conn,
+            &filter,
+            &sort.unwrap_or_default(),
+            query.page(),
+            query.limit(),
+        )
+    })
+    .await
+    {
         Ok(response) => HttpResponse::Ok().json(response),
         Err(e) => e.error_response(),
     }
@@ -23,18 +157,15 @@ pub async fn get_all(db: web::Data<Pool>, query: web::Query<PaginationQuery>) ->
 
 pub async fn get_by_id(db: web::Data<Pool>, path: web::Path<Uuid>) -> impl Responder {
     let id = path.into_inner();
-    let conn: Connection = db.get().expect("Failed to get DB connection");
 
-    match service::get_ticket_by_id(&conn, id) {
+    match db::run(&db, move |conn| service::get_ticket_by_id(conn, id)).await {
         Ok(ticket) => HttpResponse::Ok().json(ticket),
         Err(e) => e.error_response(),
     }
 }
 
 pub async fn get_stats(db: web::Data<Pool>) -> impl Responder {
-    let conn: Connection = db.get().expect("Failed to get DB connection");
-
-    match service::get_ticket_stats(&conn) {
+    match db::run(&db, service::get_ticket_stats).await {
         Ok(stats) => HttpResponse::Ok().json(serde_json::json!({
             "open": stats.open,
             "pending": stats.pending,
@@ -53,9 +184,14 @@ pub struct PostTicket {
     message: String,
 }
 
-pub async fn post_ticket(db: web::Data<Pool>, body: web::Json<PostTicket>) -> impl Responder {
+pub async fn post_ticket(
+    db: web::Data<Pool>,
+    config: web::Data<Config>,
+    number_format: web::Data<NumberFormat>,
+    notifications: web::Data<Notifications>,
+    body: web::Json<PostTicket>,
+) -> impl Responder {
     let body = body.into_inner();
-    let conn: Connection = db.get().expect("Failed to get DB connection");
 
     let req = CreateTicketRequest {
         name: body.name,
@@ -63,8 +199,22 @@ pub async fn post_ticket(db: web::Data<Pool>, body: web::Json<PostTicket>) -> im
         message: body.message,
     };
 
-    match service::create_ticket(&conn, req).await {
-        Ok(ticket) => HttpResponse::Created().json(ticket),
+    match db::run(&db, move |conn| {
+        service::create_ticket(
+            conn,
+            &number_format,
+            &notifications,
+            &config.validation,
+            req,
+            ACTOR_REQUESTER,
+        )
+    })
+    .await
+    {
+        Ok((ticket, access_token)) => HttpResponse::Created().json(CreatedTicket {
+            access_token,
+            ticket: RequesterTicket::new(ticket, Vec::new()),
+        }),
         Err(e) => e.error_response(),
     }
 }
@@ -72,18 +222,19 @@ pub async fn post_ticket(db: web::Data<Pool>, body: web::Json<PostTicket>) -> im
 #[derive(Debug, Deserialize)]
 pub struct PatchTicket {
     pub note: Option<String>,
-    pub status: Option<String>,
+    pub status: Option<TicketStatus>,
     pub notify: bool,
 }
 
 pub async fn patch_ticket(
     db: web::Data<Pool>,
+    notifications: web::Data<Notifications>,
+    principal: Principal,
     path: web::Path<Uuid>,
     body: web::Json<PatchTicket>,
 ) -> impl Responder {
     let id = path.into_inner();
     let body = body.into_inner();
-    let conn: Connection = db.get().expect("Failed to get DB connection");
 
     let req = UpdateTicketRequest {
         note: body.note,
@@ -91,17 +242,24 @@ pub async fn patch_ticket(
         notify: body.notify,
     };
 
-    match service::update_ticket(&conn, id, req).await {
+    match db::run(&db, move |conn| {
+        service::update_ticket(conn, &notifications, id, req, &principal)
+    })
+    .await
+    {
         Ok(ticket) => HttpResponse::Ok().json(ticket),
         Err(e) => e.error_response(),
     }
 }
 
-pub async fn delete_ticket(db: web::Data<Pool>, path: web::Path<Uuid>) -> impl Responder {
+pub async fn delete_ticket(
+    db: web::Data<Pool>,
+    user: User,
+    path: web::Path<Uuid>,
+) -> impl Responder {
     let id = path.into_inner();
-    let conn: Connection = db.get().expect("Failed to get DB connection");
 
-    match service::delete_ticket(&conn, id) {
+    match db::run(&db, move |conn| service::delete_ticket(conn, id, &user)).await {
         Ok(()) => HttpResponse::NoContent().finish(),
         Err(e) => e.error_response(),
     }
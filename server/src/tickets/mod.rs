@@ -1,17 +1,40 @@
+use actix_web::http::{header, StatusCode};
 use actix_web::HttpResponse;
 
+use models::TicketStatus;
+
+use crate::utils::problem::Problem;
+use crate::utils::validation::{FieldError, ValidationErrors};
+
 pub mod handlers;
 pub mod models;
-mod repository;
+pub mod numbering;
+pub(crate) mod repository;
 pub mod routes;
 mod service;
 
+/// Seconds a client is told to wait when no database connection is available
+const UNAVAILABLE_RETRY_AFTER: u64 = 2;
+
 #[derive(Debug)]
 pub enum ServiceError {
     Database(rusqlite::Error),
     NotFound,
-    #[allow(dead_code)]
+    /// Missing, unknown or expired credentials
+    Unauthorized,
+    /// The request is malformed or breaks a business rule
+    Invalid(String),
+    /// Some fields of the request are missing or invalid
+    Validation(Vec<FieldError>),
+    InvalidTransition {
+        from: TicketStatus,
+        to: TicketStatus,
+    },
+    /// The request conflicts with the current state of the resource
+    Conflict(String),
     Internal(String),
+    /// Every database connection stayed busy, the client should retry shortly
+    Unavailable,
 }
 
 impl From<rusqlite::Error> for ServiceError {
@@ -23,24 +46,109 @@ impl From<rusqlite::Error> for ServiceError {
     }
 }
 
+impl From<ValidationErrors> for ServiceError {
+    fn from(err: ValidationErrors) -> Self {
+        ServiceError::Validation(err.0)
+    }
+}
+
+impl From<r2d2::Error> for ServiceError {
+    fn from(err: r2d2::Error) -> Self {
+        log::warn!("No database connection available: {}", err);
+        ServiceError::Unavailable
+    }
+}
+
+impl From<minijinja::Error> for ServiceError {
+    fn from(err: minijinja::Error) -> Self {
+        ServiceError::Internal(format!("Failed to render email: {:#}", err))
+    }
+}
+
 impl std::fmt::Display for ServiceError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ServiceError::NotFound => write!(f, "Resource not found"),
+            ServiceError::Unauthorized => write!(f, "Invalid or missing credentials"),
+            ServiceError::Invalid(msg) => write!(f, "{}", msg),
+            ServiceError::Validation(_) => write!(f, "Some fields are invalid"),
             ServiceError::Database(e) => write!(f, "Database error: {}", e),
+            ServiceError::InvalidTransition { from, to } => {
+                write!(f, "Cannot change ticket status from {} to {}", from, to)
+            }
+            ServiceError::Conflict(msg) => write!(f, "{}", msg),
             ServiceError::Internal(msg) => write!(f, "Internal server error: {}", msg),
+            ServiceError::Unavailable => write!(f, "The service is busy, try again shortly"),
+        }
+    }
+}
+
+impl ServiceError {
+    /// The problem sent to the client. Database and internal errors are logged with
+    /// the request id and only described generically.
+    pub fn problem(&self) -> Problem {
+        match self {
+            ServiceError::NotFound => {
+                Problem::new(StatusCode::NOT_FOUND, "not_found").detail(self.to_string())
+            }
+            ServiceError::Unauthorized => {
+                Problem::new(StatusCode::UNAUTHORIZED, "unauthorized").detail(self.to_string())
+            }
+            ServiceError::Invalid(msg) => {
+                Problem::new(StatusCode::BAD_REQUEST, "invalid_request").detail(msg.clone())
+            }
+            ServiceError::Validation(fields) => {
+                Problem::new(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed")
+                    .detail(self.to_string())
+                    .extension("fields", fields)
+            }
+            ServiceError::InvalidTransition { from, to } => {
+                Problem::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_transition")
+                    .detail(self.to_string())
+                    .extension("from", from)
+                    .extension("to", to)
+                    .extension("allowed", from.allowed_transitions())
+            }
+            ServiceError::Conflict(msg) => {
+                Problem::new(StatusCode::CONFLICT, "conflict").detail(msg.clone())
+            }
+            ServiceError::Database(_) => {
+                Problem::new(StatusCode::INTERNAL_SERVER_ERROR, "database_error")
+                    .detail("The request could not be completed, please try again later")
+                    .log_cause(self)
+            }
+            ServiceError::Internal(_) => {
+                Problem::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error")
+                    .detail("The request could not be completed, please try again later")
+                    .log_cause(self)
+            }
+            ServiceError::Unavailable => {
+                Problem::new(StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+                    .detail(self.to_string())
+                    .header(header::RETRY_AFTER, UNAVAILABLE_RETRY_AFTER.to_string())
+            }
         }
     }
 }
 
 impl actix_web::ResponseError for ServiceError {
-    fn error_response(&self) -> HttpResponse {
+    fn status_code(&self) -> StatusCode {
         match self {
-            ServiceError::NotFound => HttpResponse::NotFound().finish(),
-            ServiceError::Database(e) => {
-                HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+            ServiceError::NotFound => StatusCode::NOT_FOUND,
+            ServiceError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ServiceError::Invalid(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Validation(_) | ServiceError::InvalidTransition { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
             }
-            ServiceError::Internal(msg) => HttpResponse::InternalServerError().body(msg.clone()),
+            ServiceError::Conflict(_) => StatusCode::CONFLICT,
+            ServiceError::Database(_) | ServiceError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            ServiceError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
         }
     }
+
+    fn error_response(&self) -> HttpResponse {
+        self.problem().response()
+    }
 }
@@ -1,17 +1,303 @@
 use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::utils::pagination::CursorKey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketStatus {
+    Open,
+    Pending,
+    Closed,
+}
+
+impl TicketStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TicketStatus::Open => "open",
+            TicketStatus::Pending => "pending",
+            TicketStatus::Closed => "closed",
+        }
+    }
+
+    /// States a ticket may move to from this one.
+    /// A closed ticket can only be reopened, which brings it back to `open`.
+    pub fn allowed_transitions(&self) -> &'static [TicketStatus] {
+        match self {
+            TicketStatus::Open => &[TicketStatus::Pending, TicketStatus::Closed],
+            TicketStatus::Pending => &[TicketStatus::Open, TicketStatus::Closed],
+            TicketStatus::Closed => &[TicketStatus::Open],
+        }
+    }
+
+    pub fn can_transition_to(&self, next: TicketStatus) -> bool {
+        self.allowed_transitions().contains(&next)
+    }
+}
+
+impl std::fmt::Display for TicketStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TicketStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(TicketStatus::Open),
+            "pending" => Ok(TicketStatus::Pending),
+            "closed" => Ok(TicketStatus::Closed),
+            other => Err(format!("Unknown ticket status: {}", other)),
+        }
+    }
+}
+
+impl ToSql for TicketStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for TicketStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ticket {
     pub uuid: Uuid,
     pub number: u32,
+    pub reference: String,
     pub name: String,
     pub email: String,
     pub message: String,
     pub note: Option<String>,
-    pub status: String,
+    pub status: TicketStatus,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub closed_at: Option<DateTime<Utc>>,
+    pub reopened_count: u32,
+    /// Seconds between creation and the last close, `None` while the ticket is not closed.
+    #[serde(skip_deserializing)]
+    pub time_to_close: Option<i64>,
+}
+
+impl Ticket {
+    pub fn cursor_key(&self) -> CursorKey {
+        CursorKey {
+            created_at: self.created_at,
+            uuid: self.uuid,
+        }
+    }
+
+    /// Moves the ticket to `status`, keeping the lifecycle timestamps in sync.
+    pub fn set_status(&mut self, status: TicketStatus, now: DateTime<Utc>) {
+        if status == self.status {
+            return;
+        }
+        match status {
+            TicketStatus::Closed => self.closed_at = Some(now),
+            _ if self.status == TicketStatus::Closed => {
+                self.closed_at = None;
+                self.reopened_count += 1;
+            }
+            _ => {}
+        }
+        self.status = status;
+        self.time_to_close = self.compute_time_to_close();
+    }
+
+    pub fn compute_time_to_close(&self) -> Option<i64> {
+        self.closed_at
+            .map(|closed_at| (closed_at - self.created_at).num_seconds())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub ticket: Ticket,
+    /// BM25 score, lower is more relevant
+    pub rank: f64,
+    /// Matching excerpt, HTML escaped with matches wrapped in `<mark>`
+    pub snippet: String,
+}
+
+/// Half-open date range, `after` is inclusive and `before` exclusive.
+#[derive(Debug, Clone, Default)]
+pub struct DateRange {
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TicketFilter {
+    /// Any of these statuses, every status when empty
+    pub statuses: Vec<TicketStatus>,
+    pub email: Option<String>,
+    pub created: DateRange,
+    pub updated: DateRange,
+    pub closed: DateRange,
+    pub number_min: Option<u32>,
+    pub number_max: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    CreatedAt,
+    UpdatedAt,
+    ClosedAt,
+    Number,
+    Status,
+}
+
+impl SortField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            SortField::CreatedAt => "created_at",
+            SortField::UpdatedAt => "updated_at",
+            SortField::ClosedAt => "closed_at",
+            SortField::Number => "number",
+            SortField::Status => "status",
+        }
+    }
+}
+
+impl std::str::FromStr for SortField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created_at" => Ok(SortField::CreatedAt),
+            "updated_at" => Ok(SortField::UpdatedAt),
+            "closed_at" => Ok(SortField::ClosedAt),
+            "number" => Ok(SortField::Number),
+            "status" => Ok(SortField::Status),
+            other => Err(format!(
+                "Cannot sort by {}, expected one of created_at, updated_at, closed_at, number, status",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TicketSort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Default for TicketSort {
+    fn default() -> Self {
+        Self {
+            field: SortField::CreatedAt,
+            descending: true,
+        }
+    }
+}
+
+impl std::str::FromStr for TicketSort {
+    type Err = String;
+
+    /// Parses `field` for ascending order or `-field` for descending order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('-') {
+            Some(field) => Ok(Self {
+                field: field.parse()?,
+                descending: true,
+            }),
+            None => Ok(Self {
+                field: s.parse()?,
+                descending: false,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUSES: [TicketStatus; 3] = [
+        TicketStatus::Open,
+        TicketStatus::Pending,
+        TicketStatus::Closed,
+    ];
+
+    fn ticket(status: TicketStatus, created_at: DateTime<Utc>) -> Ticket {
+        Ticket {
+            uuid: Uuid::new_v4(),
+            number: 1,
+            reference: "1".to_string(),
+            name: "Name".to_string(),
+            email: "a@example.org".to_string(),
+            message: "Message".to_string(),
+            note: None,
+            status,
+            created_at,
+            updated_at: None,
+            closed_at: None,
+            reopened_count: 0,
+            time_to_close: None,
+        }
+    }
+
+    #[test]
+    fn transition_table() {
+        use TicketStatus::*;
+        let allowed = [
+            (Open, Pending),
+            (Open, Closed),
+            (Pending, Open),
+            (Pending, Closed),
+            (Closed, Open),
+        ];
+        for from in STATUSES {
+            for to in STATUSES {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn status_round_trips_through_its_name() {
+        for status in STATUSES {
+            assert_eq!(status.as_str().parse::<TicketStatus>(), Ok(status));
+        }
+        assert!("archived".parse::<TicketStatus>().is_err());
+    }
+
+    #[test]
+    fn closing_and_reopening_keep_timestamps_in_sync() {
+        let created_at = Utc::now();
+        let mut ticket = ticket(TicketStatus::Open, created_at);
+
+        let closed_at = created_at + chrono::Duration::seconds(90);
+        ticket.set_status(TicketStatus::Closed, closed_at);
+        assert_eq!(ticket.closed_at, Some(closed_at));
+        assert_eq!(ticket.time_to_close, Some(90));
+
+        ticket.set_status(TicketStatus::Open, closed_at);
+        assert_eq!(ticket.closed_at, None);
+        assert_eq!(ticket.time_to_close, None);
+        assert_eq!(ticket.reopened_count, 1);
+
+        // Not a change, nothing to record
+        ticket.set_status(TicketStatus::Open, closed_at);
+        assert_eq!(ticket.reopened_count, 1);
+    }
 }
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Datelike, Utc};
+
+pub const DEFAULT_FORMAT: &str = "{number}";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Number { width: usize },
+    Year,
+    Month,
+}
+
+/// Format used to build the human readable ticket reference, e.g. `SUP-{year}-{number:05}`.
+///
+/// Supported placeholders are `{number}` (optionally zero padded with `{number:05}`),
+/// `{year}` and `{month}`, the latter two taken from the ticket creation date.
+#[derive(Debug, Clone)]
+pub struct NumberFormat {
+    segments: Vec<Segment>,
+}
+
+impl NumberFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = format;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').map(|i| start + i).ok_or_else(|| {
+                format!("Unclosed placeholder in ticket number format: {}", format)
+            })?;
+
+            segments.push(match &rest[start + 1..end] {
+                "number" => Segment::Number { width: 0 },
+                "year" => Segment::Year,
+                "month" => Segment::Month,
+                placeholder => match placeholder.strip_prefix("number:") {
+                    Some(width) => Segment::Number {
+                        width: width
+                            .parse()
+                            .map_err(|_| format!("Invalid number width: {}", width))?,
+                    },
+                    None => return Err(format!("Unknown placeholder: {{{}}}", placeholder)),
+                },
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        if !segments.iter().any(|s| matches!(s, Segment::Number { .. })) {
+            return Err(format!(
+                "Ticket number format must contain {{number}}: {}",
+                format
+            ));
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn render(&self, number: u32, created_at: DateTime<Utc>) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Number { width } => format!("{:0width$}", number, width = width),
+                Segment::Year => created_at.year().to_string(),
+                Segment::Month => format!("{:02}", created_at.month()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn render(format: &str, number: u32) -> String {
+        let created_at = Utc.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap();
+        NumberFormat::parse(format)
+            .unwrap()
+            .render(number, created_at)
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        assert_eq!(render(DEFAULT_FORMAT, 42), "42");
+        assert_eq!(render("SUP-{year}-{number:05}", 42), "SUP-2024-00042");
+        assert_eq!(render("{year}{month}-{number}", 7), "202403-7");
+        // Padding never truncates
+        assert_eq!(render("{number:02}", 12345), "12345");
+    }
+
+    #[test]
+    fn rejects_invalid_formats() {
+        for format in [
+            "SUP-{year}",
+            "{number",
+            "{day}-{number}",
+            "{number:five}",
+            "",
+        ] {
+            assert!(NumberFormat::parse(format).is_err(), "{:?}", format);
+        }
+    }
+}
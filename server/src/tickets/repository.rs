@@ -1,10 +1,14 @@
-use rusqlite::params;
+use rusqlite::{params, params_from_iter, Connection, ToSql};
 use uuid::Uuid;
 
-use super::models::Ticket;
-use crate::utils::db::Connection;
+use super::models::{DateRange, SearchResult, Ticket, TicketFilter, TicketSort, TicketStatus};
+use crate::utils::pagination::Cursor;
 
-fn parse_uuid(uuid_str: &str) -> Result<Uuid, rusqlite::Error> {
+/// Delimiters placed around matches by `search`, replaced once the snippet is escaped.
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+
+pub(crate) fn parse_uuid(uuid_str: &str) -> Result<Uuid, rusqlite::Error> {
     Uuid::parse_str(uuid_str).map_err(|e| {
         rusqlite::Error::FromSqlConversionFailure(
             0, // column index
@@ -14,99 +18,266 @@ fn parse_uuid(uuid_str: &str) -> Result<Uuid, rusqlite::Error> {
     })
 }
 
+fn ticket_from_row(row: &rusqlite::Row) -> Result<Ticket, rusqlite::Error> {
+    let mut ticket = Ticket {
+        uuid: {
+            let uuid_str: String = row.get("uuid")?;
+            parse_uuid(&uuid_str)?
+        },
+        number: row.get("number")?,
+        reference: row.get("reference")?,
+        name: row.get("name")?,
+        email: row.get("email")?,
+        message: row.get("message")?,
+        note: row.get("note")?,
+        status: row.get("status")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        closed_at: row.get("closed_at")?,
+        reopened_count: row.get("reopened_count")?,
+        time_to_close: None,
+    };
+    ticket.time_to_close = ticket.compute_time_to_close();
+    Ok(ticket)
+}
+
 pub fn get_by_id(conn: &Connection, id: Uuid) -> Result<Ticket, rusqlite::Error> {
     let mut stmt = conn.prepare("SELECT * FROM tickets WHERE uuid = ?1;")?;
-    stmt.query_row([&id.to_string()], |row| {
-        Ok(Ticket {
-            uuid: {
-                let uuid_str: String = row.get("uuid")?;
-                parse_uuid(&uuid_str)?
-            },
-            number: row.get("number")?,
-            name: row.get("name")?,
-            email: row.get("email")?,
-            message: row.get("message")?,
-            note: row.get("note")?,
-            status: row.get("status")?,
-            created_at: row.get("created_at")?,
-            updated_at: row.get("updated_at")?,
-            closed_at: row.get("closed_at")?,
-        })
-    })
+    stmt.query_row([&id.to_string()], ticket_from_row)
+}
+
+/// Conditions and their parameters shared by the list, search and count queries.
+struct FilterSql {
+    conditions: Vec<String>,
+    params: Vec<Box<dyn ToSql>>,
+}
+
+impl FilterSql {
+    fn new(filter: &TicketFilter) -> Self {
+        let mut sql = Self {
+            conditions: Vec::new(),
+            params: Vec::new(),
+        };
+
+        if !filter.statuses.is_empty() {
+            let placeholders = vec!["?"; filter.statuses.len()].join(", ");
+            sql.conditions
+                .push(format!("tickets.status IN ({})", placeholders));
+            for status in &filter.statuses {
+                sql.params.push(Box::new(*status));
+            }
+        }
+        if let Some(email) = &filter.email {
+            sql.push("tickets.email = ? COLLATE NOCASE", email.clone());
+        }
+        sql.push_range("tickets.created_at", &filter.created);
+        sql.push_range("tickets.updated_at", &filter.updated);
+        sql.push_range("tickets.closed_at", &filter.closed);
+        if let Some(min) = filter.number_min {
+            sql.push("tickets.number >= ?", min);
+        }
+        if let Some(max) = filter.number_max {
+            sql.push("tickets.number <= ?", max);
+        }
+        sql
+    }
+
+    fn push<T: ToSql + 'static>(&mut self, condition: &str, param: T) {
+        self.conditions.push(condition.to_string());
+        self.params.push(Box::new(param));
+    }
+
+    // Timestamps are all written by chrono in the same format, so they compare as text
+    fn push_range(&mut self, column: &str, range: &DateRange) {
+        if let Some(after) = range.after {
+            self.push(&format!("{} >= ?", column), after);
+        }
+        if let Some(before) = range.before {
+            self.push(&format!("{} < ?", column), before);
+        }
+    }
+
+    /// Renders the conditions prefixed by `keyword`, or nothing when there are none.
+    fn clause(&self, keyword: &str) -> String {
+        if self.conditions.is_empty() {
+            String::new()
+        } else {
+            format!("{} {}", keyword, self.conditions.join(" AND "))
+        }
+    }
 }
 
-pub fn get_all(conn: &Connection, page: u32, limit: u32) -> Result<Vec<Ticket>, rusqlite::Error> {
-    let mut stmt =
-        conn.prepare("SELECT * FROM tickets ORDER BY created_at DESC LIMIT ?1 OFFSET ?2;")?;
-    stmt.query_map([limit, (page - 1) * limit], |row| {
-        Ok(Ticket {
-            uuid: {
-                let uuid_str: String = row.get("uuid")?;
-                parse_uuid(&uuid_str)?
-            },
-            number: row.get("number")?,
-            name: row.get("name")?,
-            email: row.get("email")?,
-            message: row.get("message")?,
-            note: row.get("note")?,
-            status: row.get("status")?,
-            created_at: row.get("created_at")?,
-            updated_at: row.get("updated_at")?,
-            closed_at: row.get("closed_at")?,
+fn order_by(sort: &TicketSort) -> String {
+    let direction = if sort.descending { "DESC" } else { "ASC" };
+    format!(
+        "ORDER BY tickets.{} {}, tickets.uuid {}",
+        sort.field.column(),
+        direction,
+        direction
+    )
+}
+
+pub fn get_all(
+    conn: &Connection,
+    filter: &TicketFilter,
+    sort: &TicketSort,
+    page: u32,
+    limit: u32,
+) -> Result<Vec<Ticket>, rusqlite::Error> {
+    let mut sql = FilterSql::new(filter);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM tickets {} {} LIMIT ? OFFSET ?;",
+        sql.clause("WHERE"),
+        order_by(sort)
+    ))?;
+    sql.params.push(Box::new(limit));
+    sql.params.push(Box::new((page - 1) * limit));
+    stmt.query_map(params_from_iter(sql.params), ticket_from_row)
+        .and_then(Iterator::collect)
+}
+
+/// Keyset pagination over the creation date, fetching at most `limit` items next to the cursor.
+/// Items are always returned newest first.
+pub fn get_by_cursor(
+    conn: &Connection,
+    filter: &TicketFilter,
+    cursor: &Cursor,
+    limit: u32,
+) -> Result<Vec<Ticket>, rusqlite::Error> {
+    let (key, comparison, direction) = match cursor {
+        Cursor::After(key) => (key, "<", "DESC"),
+        Cursor::Before(key) => (key, ">", "ASC"),
+    };
+
+    let mut sql = FilterSql::new(filter);
+    sql.conditions.push(format!(
+        "(tickets.created_at, tickets.uuid) {} (?, ?)",
+        comparison
+    ));
+    sql.params.push(Box::new(key.created_at));
+    sql.params.push(Box::new(key.uuid.to_string()));
+    sql.params.push(Box::new(limit));
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM tickets {} ORDER BY tickets.created_at {}, tickets.uuid {} LIMIT ?;",
+        sql.clause("WHERE"),
+        direction,
+        direction
+    ))?;
+    let mut tickets: Vec<Ticket> = stmt
+        .query_map(params_from_iter(sql.params), ticket_from_row)?
+        .collect::<Result<_, _>>()?;
+
+    if matches!(cursor, Cursor::Before(_)) {
+        tickets.reverse();
+    }
+    Ok(tickets)
+}
+
+/// Full-text search, `query` must already be a valid FTS5 expression.
+/// Results are ordered by relevance unless `sort` is given.
+pub fn search(
+    conn: &Connection,
+    query: &str,
+    filter: &TicketFilter,
+    sort: Option<&TicketSort>,
+    page: u32,
+    limit: u32,
+) -> Result<Vec<SearchResult>, rusqlite::Error> {
+    let filter_sql = FilterSql::new(filter);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tickets.*,
+            bm25(tickets_fts, 10.0, 5.0, 5.0, 2.0, 1.0) AS rank,
+            snippet(tickets_fts, -1, ?, ?, '…', 12) AS snippet
+         FROM tickets_fts
+         JOIN tickets ON tickets.number = tickets_fts.rowid
+         WHERE tickets_fts MATCH ? {}
+         {}
+         LIMIT ? OFFSET ?;",
+        filter_sql.clause("AND"),
+        sort.map(order_by)
+            .unwrap_or_else(|| "ORDER BY rank".to_string())
+    ))?;
+
+    let mut params: Vec<Box<dyn ToSql>> = vec![
+        Box::new(MATCH_START.to_string()),
+        Box::new(MATCH_END.to_string()),
+        Box::new(query.to_string()),
+    ];
+    params.extend(filter_sql.params);
+    params.push(Box::new(limit));
+    params.push(Box::new((page - 1) * limit));
+
+    stmt.query_map(params_from_iter(params), |row| {
+        Ok(SearchResult {
+            ticket: ticket_from_row(row)?,
+            rank: row.get("rank")?,
+            snippet: row.get("snippet")?,
         })
     })
     .and_then(Iterator::collect)
 }
 
-pub fn get_count(conn: &Connection) -> Result<i64, rusqlite::Error> {
-    let mut stmt = conn.prepare("SELECT COUNT(*) FROM tickets;")?;
-    stmt.query_row([], |row| row.get(0))
+pub fn get_search_count(
+    conn: &Connection,
+    query: &str,
+    filter: &TicketFilter,
+) -> Result<i64, rusqlite::Error> {
+    let filter_sql = FilterSql::new(filter);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT COUNT(*) FROM tickets_fts
+         JOIN tickets ON tickets.number = tickets_fts.rowid
+         WHERE tickets_fts MATCH ? {};",
+        filter_sql.clause("AND")
+    ))?;
+    let mut params: Vec<Box<dyn ToSql>> = vec![Box::new(query.to_string())];
+    params.extend(filter_sql.params);
+    stmt.query_row(params_from_iter(params), |row| row.get(0))
 }
 
-pub fn get_count_by_status(conn: &Connection, status: &str) -> Result<i64, rusqlite::Error> {
-    if !["open", "pending", "closed"].contains(&status) {
-        return Err(rusqlite::Error::InvalidQuery);
-    }
+pub fn get_count(conn: &Connection, filter: &TicketFilter) -> Result<i64, rusqlite::Error> {
+    let sql = FilterSql::new(filter);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT COUNT(*) FROM tickets {};",
+        sql.clause("WHERE")
+    ))?;
+    stmt.query_row(params_from_iter(sql.params), |row| row.get(0))
+}
 
+pub fn get_count_by_status(
+    conn: &Connection,
+    status: TicketStatus,
+) -> Result<i64, rusqlite::Error> {
     let mut stmt = conn.prepare("SELECT COUNT(*) FROM tickets WHERE status = ?1;")?;
     stmt.query_row([status], |row| row.get(0))
 }
 
 pub fn get_last(conn: &Connection) -> Result<Ticket, rusqlite::Error> {
     let mut stmt = conn.prepare("SELECT * FROM tickets ORDER BY created_at DESC LIMIT 1")?;
-    stmt.query_row([], |row| {
-        Ok(Ticket {
-            uuid: {
-                let uuid_str: String = row.get("uuid")?;
-                parse_uuid(&uuid_str)?
-            },
-            number: row.get("number")?,
-            name: row.get("name")?,
-            email: row.get("email")?,
-            message: row.get("message")?,
-            note: row.get("note")?,
-            status: row.get("status")?,
-            created_at: row.get("created_at")?,
-            updated_at: row.get("updated_at")?,
-            closed_at: row.get("closed_at")?,
-        })
-    })
+    stmt.query_row([], ticket_from_row)
 }
 
-pub fn get_max_number(conn: &Connection) -> Result<Option<i64>, rusqlite::Error> {
-    let mut stmt = conn.prepare("SELECT MAX(number) as max FROM tickets;")?;
+/// Reserves the next ticket number, must run inside the transaction inserting the ticket.
+pub fn next_number(conn: &Connection) -> Result<u32, rusqlite::Error> {
+    // No RETURNING, it needs SQLite 3.35 and the system library may be older
+    conn.execute(
+        "UPDATE ticket_sequence SET value = value + 1 WHERE id = 1;",
+        [],
+    )?;
+    let mut stmt = conn.prepare("SELECT value FROM ticket_sequence WHERE id = 1;")?;
     stmt.query_row([], |row| row.get(0))
 }
 
 pub fn create(conn: &Connection, ticket: &Ticket) -> Result<(), rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "INSERT INTO tickets (uuid, number, name, email, message, note, status, created_at, updated_at, closed_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);"
+        "INSERT INTO tickets (uuid, number, reference, name, email, message, note, status, created_at, updated_at, closed_at, reopened_count)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12);"
     )?;
 
     stmt.execute(params![
         ticket.uuid.to_string(),
         ticket.number,
+        ticket.reference,
         ticket.name,
         ticket.email,
         ticket.message,
@@ -115,6 +286,7 @@ pub fn create(conn: &Connection, ticket: &Ticket) -> Result<(), rusqlite::Error>
         ticket.created_at,
         ticket.updated_at,
         ticket.closed_at,
+        ticket.reopened_count,
     ])?;
 
     Ok(())
@@ -122,7 +294,7 @@ pub fn create(conn: &Connection, ticket: &Ticket) -> Result<(), rusqlite::Error>
 
 pub fn update(conn: &Connection, id: &Uuid, tickets: &Ticket) -> Result<(), rusqlite::Error> {
     let mut stmt = conn.prepare(
-        "UPDATE tickets SET name = ?1, email = ?2, message = ?3, note = ?4, status = ?5, updated_at = ?6 WHERE uuid = ?7;"
+        "UPDATE tickets SET name = ?1, email = ?2, message = ?3, note = ?4, status = ?5, updated_at = ?6, closed_at = ?7, reopened_count = ?8 WHERE uuid = ?9;"
     )?;
 
     stmt.execute(params![
@@ -132,6 +304,8 @@ pub fn update(conn: &Connection, id: &Uuid, tickets: &Ticket) -> Result<(), rusq
         tickets.note,
         tickets.status,
         tickets.updated_at,
+        tickets.closed_at,
+        tickets.reopened_count,
         id.to_string(),
     ])?;
 
@@ -143,3 +317,18 @@ pub fn delete(conn: &Connection, id: &Uuid) -> Result<(), rusqlite::Error> {
     stmt.execute([id.to_string()])?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::db;
+
+    #[test]
+    fn next_number_reserves_consecutive_numbers() {
+        let pool = db::test_pool();
+        let conn = pool.get().unwrap();
+        let first = next_number(&conn).unwrap();
+        assert_eq!(next_number(&conn).unwrap(), first + 1);
+        assert_eq!(next_number(&conn).unwrap(), first + 2);
+    }
+}
@@ -1,21 +1,41 @@
 use super::handlers;
+use crate::api_keys::models::Scope;
+use crate::middlewares::auth::RequireRole;
+use crate::users::models::Role;
 use actix_web::web;
 
+// Routes open to integrations name the scope an API key needs, the others are staff only.
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::resource("/stats")
-            .wrap(crate::middlewares::auth::AdminAuth)
+            .wrap(RequireRole::new(Role::Viewer).or_scope(Scope::StatsRead))
             .route(web::get().to(handlers::get_stats)),
     );
     cfg.service(
         web::resource("/tickets")
             .route(web::post().to(handlers::post_ticket))
-            .route(web::get().to(handlers::get_all).wrap(crate::middlewares::auth::AdminAuth)),
+            .route(
+                web::get()
+                    .to(handlers::get_all)
+                    .wrap(RequireRole::new(Role::Viewer).or_scope(Scope::TicketsRead)),
+            ),
     );
     cfg.service(
         web::resource("/tickets/{id}")
-            .route(web::get().to(handlers::get_by_id))
-            .route(web::patch().to(handlers::patch_ticket).wrap(crate::middlewares::auth::AdminAuth))
-            .route(web::delete().to(handlers::delete_ticket).wrap(crate::middlewares::auth::AdminAuth)),
+            .route(
+                web::get()
+                    .to(handlers::get_by_id)
+                    .wrap(RequireRole::new(Role::Viewer).or_scope(Scope::TicketsRead)),
+            )
+            .route(
+                web::patch()
+                    .to(handlers::patch_ticket)
+                    .wrap(RequireRole::new(Role::Agent).or_scope(Scope::TicketsWrite)),
+            )
+            .route(
+                web::delete()
+                    .to(handlers::delete_ticket)
+                    .wrap(RequireRole::new(Role::Admin)),
+            ),
     );
 }
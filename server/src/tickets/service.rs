@@ -1,8 +1,17 @@
 use super::{repository, ServiceError};
-use crate::tickets::models::Ticket;
-use crate::utils::brevo::{send_notification, send_ticket};
+use crate::history::service as history;
+use crate::messages::models::{MessageKind, TicketMessage};
+use crate::messages::repository as messages_repository;
+use crate::outbox::service as outbox;
+use crate::requester::service as requester;
+use crate::tickets::models::{SearchResult, Ticket, TicketFilter, TicketSort, TicketStatus};
+use crate::tickets::numbering::NumberFormat;
+use crate::users::models::{Principal, User};
 use crate::utils::db::Connection;
-use crate::utils::pagination::PaginatedResponse;
+use crate::utils::notifications::Notifications;
+use crate::utils::pagination::{Cursor, PaginatedResponse};
+use crate::utils::validation::{self, ValidationErrors, ValidationSettings, Validator};
+use rusqlite::{Transaction, TransactionBehavior};
 use uuid::Uuid;
 
 pub struct TicketStats {
@@ -15,13 +24,115 @@ pub struct TicketStats {
 
 pub fn get_all_tickets(
     conn: &Connection,
+    filter: &TicketFilter,
+    sort: &TicketSort,
     page: u32,
     limit: u32,
 ) -> Result<PaginatedResponse<Ticket>, ServiceError> {
-    let tickets = repository::get_all(conn, page, limit)?;
-    let total = repository::get_count(conn).unwrap_or(0) as u32;
+    let tickets = repository::get_all(conn, filter, sort, page, limit)?;
+    let total = repository::get_count(conn, filter)? as u32;
 
-    Ok(PaginatedResponse::new(tickets, page, limit, total))
+    // Cursors are keyed on the creation date, they only make sense in the default order
+    let (next, prev) = if *sort == TicketSort::default() {
+        (
+            tickets
+                .last()
+                .filter(|_| page * limit < total)
+                .map(Ticket::cursor_key),
+            tickets.first().filter(|_| page > 1).map(Ticket::cursor_key),
+        )
+    } else {
+        (None, None)
+    };
+
+    Ok(PaginatedResponse::new(tickets, page, limit, total).cursors(next, prev))
+}
+
+pub fn get_tickets_by_cursor(
+    conn: &Connection,
+    filter: &TicketFilter,
+    cursor: &Cursor,
+    limit: u32,
+) -> Result<PaginatedResponse<Ticket>, ServiceError> {
+    // Fetch one extra item to know whether there is more beyond this page
+    let mut tickets = repository::get_by_cursor(conn, filter, cursor, limit + 1)?;
+    let has_more = tickets.len() > limit as usize;
+    let total = repository::get_count(conn, filter)? as u32;
+
+    let (next, prev) = match cursor {
+        Cursor::After(_) => {
+            tickets.truncate(limit as usize);
+            (
+                tickets.last().filter(|_| has_more).map(Ticket::cursor_key),
+                tickets.first().map(Ticket::cursor_key),
+            )
+        }
+        Cursor::Before(_) => {
+            if has_more {
+                tickets.remove(0);
+            }
+            (
+                tickets.last().map(Ticket::cursor_key),
+                tickets.first().filter(|_| has_more).map(Ticket::cursor_key),
+            )
+        }
+    };
+
+    Ok(PaginatedResponse::with_cursors(
+        tickets, limit, total, next, prev,
+    ))
+}
+
+/// Turns free text into an FTS5 expression matching every word as a prefix,
+/// so user input can never be interpreted as query syntax.
+fn to_fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+fn highlight_snippet(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            repository::MATCH_START => html.push_str("<mark>"),
+            repository::MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+pub fn search_tickets(
+    conn: &Connection,
+    text: &str,
+    filter: &TicketFilter,
+    sort: Option<&TicketSort>,
+    page: u32,
+    limit: u32,
+) -> Result<PaginatedResponse<SearchResult>, ServiceError> {
+    let Some(query) = to_fts_query(text) else {
+        return Ok(PaginatedResponse::new(Vec::new(), page, limit, 0));
+    };
+
+    let mut results = repository::search(conn, &query, filter, sort, page, limit)?;
+    for result in &mut results {
+        result.snippet = highlight_snippet(&result.snippet);
+    }
+    let total = repository::get_search_count(conn, &query, filter)? as u32;
+
+    Ok(PaginatedResponse::new(results, page, limit, total))
 }
 
 pub fn get_ticket_by_id(conn: &Connection, id: Uuid) -> Result<Ticket, ServiceError> {
@@ -29,9 +140,9 @@ pub fn get_ticket_by_id(conn: &Connection, id: Uuid) -> Result<Ticket, ServiceEr
 }
 
 pub fn get_ticket_stats(conn: &Connection) -> Result<TicketStats, ServiceError> {
-    let open = repository::get_count_by_status(conn, "open").unwrap_or(0);
-    let pending = repository::get_count_by_status(conn, "pending").unwrap_or(0);
-    let closed = repository::get_count_by_status(conn, "closed").unwrap_or(0);
+    let open = repository::get_count_by_status(conn, TicketStatus::Open).unwrap_or(0);
+    let pending = repository::get_count_by_status(conn, TicketStatus::Pending).unwrap_or(0);
+    let closed = repository::get_count_by_status(conn, TicketStatus::Closed).unwrap_or(0);
     let total = open + pending + closed;
 
     let last_at = match repository::get_last(conn) {
@@ -55,62 +166,157 @@ pub struct CreateTicketRequest {
     pub message: String,
 }
 
-pub async fn create_ticket(
+impl CreateTicketRequest {
+    /// Cleans every field, or reports all the invalid ones at once.
+    pub fn validate(self, settings: &ValidationSettings) -> Result<Self, ValidationErrors> {
+        let mut validator = Validator::new();
+        let req = Self {
+            name: validator.text("name", "Name", &self.name, validation::NAME_MAX_LENGTH),
+            email: validator.email("email", &self.email, settings),
+            message: validator.multiline(
+                "message",
+                "Message",
+                &self.message,
+                validation::MESSAGE_MAX_LENGTH,
+            ),
+        };
+        validator.finish()?;
+        Ok(req)
+    }
+}
+
+pub fn create_ticket(
     conn: &Connection,
+    number_format: &NumberFormat,
+    notifications: &Notifications,
+    settings: &ValidationSettings,
     req: CreateTicketRequest,
-) -> Result<Ticket, ServiceError> {
-    let max_number = repository::get_max_number(conn)?.unwrap_or(0);
+    actor: &str,
+) -> Result<(Ticket, String), ServiceError> {
+    let req = req.validate(settings)?;
+    // Take the write lock up front so concurrent submissions are serialized
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let number = repository::next_number(&tx)?;
+    let created_at = chrono::Utc::now();
 
     let ticket = Ticket {
         uuid: Uuid::new_v4(),
-        number: max_number as u32 + 1,
+        number,
+        reference: number_format.render(number, created_at),
         name: req.name,
         email: req.email,
         message: req.message,
         note: None,
-        status: "open".to_string(),
-        created_at: chrono::Utc::now(),
+        status: TicketStatus::Open,
+        created_at,
         updated_at: None,
         closed_at: None,
+        reopened_count: 0,
+        time_to_close: None,
     };
 
-    repository::create(conn, &ticket)?;
-
-    // Send email notification (non-blocking, ignore errors)
-    let _ = send_ticket(&ticket).await;
+    repository::create(&tx, &ticket)?;
+    history::record(
+        &tx,
+        ticket.uuid,
+        "ticket",
+        None,
+        Some("created".to_string()),
+        actor,
+        created_at,
+    )?;
+    // Queued with the ticket so the confirmation is never lost or sent for a rolled back ticket
+    let token = requester::issue_token(&tx, ticket.uuid, created_at, notifications.link_ttl())?;
+    outbox::enqueue(
+        &tx,
+        &notifications.ticket_email(&ticket, &token)?,
+        created_at,
+    )?;
+    tx.commit()?;
 
-    Ok(ticket)
+    Ok((ticket, token))
 }
 
 pub struct UpdateTicketRequest {
     pub note: Option<String>,
-    pub status: Option<String>,
+    pub status: Option<TicketStatus>,
     pub notify: bool,
 }
 
-pub async fn update_ticket(
+pub fn update_ticket(
     conn: &Connection,
+    notifications: &Notifications,
     id: Uuid,
     req: UpdateTicketRequest,
+    principal: &Principal,
 ) -> Result<Ticket, ServiceError> {
-    let mut ticket = repository::get_by_id(conn, id)?;
+    // Check the transition against the row as it is under the write lock,
+    // so concurrent updates cannot both pass it
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let mut ticket = repository::get_by_id(&tx, id)?;
+    let before = ticket.clone();
+    let now = chrono::Utc::now();
 
-    // Apply updates
-    ticket.note = req.note.or(ticket.note);
-    ticket.status = req.status.unwrap_or(ticket.status);
-    ticket.updated_at = Some(chrono::Utc::now());
+    // Reject status changes that are not part of the lifecycle
+    if let Some(status) = req.status {
+        if status != ticket.status && !ticket.status.can_transition_to(status) {
+            return Err(ServiceError::InvalidTransition {
+                from: ticket.status,
+                to: status,
+            });
+        }
+        ticket.set_status(status, now);
+    }
 
-    repository::update(conn, &id, &ticket)?;
+    // Keep every note in the conversation instead of only the latest one
+    if let Some(note) = req.note.filter(|note| Some(note) != ticket.note.as_ref()) {
+        let mut message = TicketMessage {
+            id: 0,
+            ticket_uuid: ticket.uuid,
+            kind: MessageKind::InternalNote,
+            author_name: principal.name().to_string(),
+            author_email: principal.email().map(str::to_string),
+            body: note.clone(),
+            created_at: now,
+        };
+        messages_repository::create(&tx, &mut message)?;
+        ticket.note = Some(note);
+    }
+
+    // Apply updates
+    ticket.updated_at = Some(now);
 
-    // Send notification if requested (non-blocking, ignore errors)
+    repository::update(&tx, &id, &ticket)?;
+    history::record_changes(&tx, &before, &ticket, &principal.actor(), now)?;
     if req.notify {
-        let _ = send_notification(&ticket).await;
+        let token = requester::issue_token(&tx, ticket.uuid, now, notifications.link_ttl())?;
+        outbox::enqueue(
+            &tx,
+            &notifications.notification_email(&ticket, &token)?,
+            now,
+        )?;
     }
+    tx.commit()?;
 
     Ok(ticket)
 }
 
-pub fn delete_ticket(conn: &Connection, id: Uuid) -> Result<(), ServiceError> {
-    repository::delete(conn, &id)?;
+pub fn delete_ticket(conn: &Connection, id: Uuid, user: &User) -> Result<(), ServiceError> {
+    let ticket = repository::get_by_id(conn, id)?;
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    messages_repository::delete_by_ticket(&tx, &id)?;
+    requester::revoke_tokens(&tx, id)?;
+    repository::delete(&tx, &id)?;
+    history::record(
+        &tx,
+        id,
+        "ticket",
+        Some(ticket.reference),
+        Some("deleted".to_string()),
+        &user.email,
+        chrono::Utc::now(),
+    )?;
+    tx.commit()?;
     Ok(())
 }
@@ -0,0 +1,130 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::Deserialize;
+
+use super::models::{Role, User};
+use super::service::{self, CreateUserRequest, UpdateUserRequest};
+use crate::config::Config;
+use crate::middlewares::auth::{bearer_token, locked_out, unauthorized};
+use crate::tickets::ServiceError;
+use crate::utils::db;
+use crate::utils::lockout::Lockout;
+use crate::Pool;
+
+#[derive(Debug, Deserialize)]
+pub struct Login {
+    email: String,
+    password: String,
+}
+
+pub async fn login(
+    db: web::Data<Pool>,
+    config: web::Data<Config>,
+    lockout: web::Data<Lockout>,
+    req: HttpRequest,
+    body: web::Json<Login>,
+) -> impl Responder {
+    let ip = lockout.client_ip(&req);
+    if let Some(Err(retry_after)) = ip.map(|ip| lockout.check(ip)) {
+        return locked_out(retry_after);
+    }
+
+    let body = body.into_inner();
+    let email = body.email.trim().to_string();
+
+    match db::run(&db, move |conn| {
+        service::login(conn, &body.email, &body.password, config.session_ttl)
+    })
+    .await
+    {
+        Ok(session) => {
+            if let Some(ip) = ip {
+                lockout.record_success(ip);
+            }
+            HttpResponse::Ok().json(session)
+        }
+        Err(ServiceError::Unauthorized) => {
+            let from = ip.map_or("unknown address".to_string(), |ip| ip.to_string());
+            log::warn!(target: "audit", "Failed login for {} from {}", email, from);
+            if ip.is_some_and(|ip| lockout.record_failure(ip)) {
+                log::warn!(target: "audit", "Locked out {} after repeated failures", from);
+            }
+            ServiceError::Unauthorized.error_response()
+        }
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn logout(db: web::Data<Pool>, req: HttpRequest) -> impl Responder {
+    let Some(token) = bearer_token(req.headers()).map(str::to_string) else {
+        return unauthorized(None);
+    };
+
+    match db::run(&db, move |conn| service::logout(conn, &token)).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response(),
+    }
+}
+
+pub async fn get_me(user: User) -> impl Responder {
+    HttpResponse::Ok().json(user)
+}
+
+pub async fn get_users(db: web::Data<Pool>) -> impl Responder {
+    match db::run(&db, move |conn| service::get_users(conn)).await {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostUser {
+    email: String,
+    name: String,
+    password: String,
+    role: Role,
+}
+
+pub async fn post_user(db: web::Data<Pool>, body: web::Json<PostUser>) -> impl Responder {
+    let body = body.into_inner();
+
+    let req = CreateUserRequest {
+        email: body.email,
+        name: body.name,
+        password: body.password,
+        role: body.role,
+    };
+
+    match db::run(&db, move |conn| service::create_user(conn, req)).await {
+        Ok(user) => HttpResponse::Created().json(user),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchUser {
+    name: Option<String>,
+    role: Option<Role>,
+    active: Option<bool>,
+    password: Option<String>,
+}
+
+pub async fn patch_user(
+    db: web::Data<Pool>,
+    path: web::Path<i64>,
+    body: web::Json<PatchUser>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let body = body.into_inner();
+
+    let req = UpdateUserRequest {
+        name: body.name,
+        role: body.role,
+        active: body.active,
+        password: body.password,
+    };
+
+    match db::run(&db, move |conn| service::update_user(conn, id, req)).await {
+        Ok(user) => HttpResponse::Ok().json(user),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,155 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, http::StatusCode, FromRequest, HttpMessage, HttpRequest};
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+
+use crate::api_keys::models::ApiKey;
+use crate::tickets::ServiceError;
+use crate::utils::problem::Problem;
+
+/// Staff roles, each one includes the permissions of the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access to tickets and their conversation
+    Viewer,
+    /// Can update tickets and write to requesters
+    Agent,
+    /// Can delete tickets, manage users and the email outbox
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Agent => "agent",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Whether this role grants what `required` grants.
+    pub fn allows(&self, required: Role) -> bool {
+        *self >= required
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "agent" => Ok(Role::Agent),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("Unknown role: {}", other)),
+        }
+    }
+}
+
+impl ToSql for Role {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Role {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct User {
+    pub id: i64,
+    pub email: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub role: Role,
+    /// Inactive users cannot log in and lose their sessions
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+/// Whoever made an authenticated request, a staff user or an integration using an API key.
+#[derive(Debug, Clone)]
+pub enum Principal {
+    User(User),
+    ApiKey(ApiKey),
+}
+
+impl Principal {
+    /// Recorded in the ticket history, the email of a user or `api_key:<id>` for a key.
+    pub fn actor(&self) -> String {
+        match self {
+            Principal::User(user) => user.email.clone(),
+            Principal::ApiKey(api_key) => format!("api_key:{}", api_key.id),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Principal::User(user) => &user.name,
+            Principal::ApiKey(api_key) => &api_key.name,
+        }
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        match self {
+            Principal::User(user) => Some(&user.email),
+            Principal::ApiKey(_) => None,
+        }
+    }
+}
+
+/// The principal authenticated by the `RequireRole` middleware, only available on routes it wraps.
+impl FromRequest for Principal {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<Principal>()
+                .cloned()
+                .ok_or_else(|| ServiceError::Unauthorized.into()),
+        )
+    }
+}
+
+/// The authenticated staff user, API keys are refused.
+impl FromRequest for User {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(match req.extensions().get::<Principal>() {
+            Some(Principal::User(user)) => Ok(user.clone()),
+            Some(Principal::ApiKey(_)) => Err(Problem::new(StatusCode::FORBIDDEN, "forbidden")
+                .detail("Not available to API keys")
+                .into()),
+            None => Err(ServiceError::Unauthorized.into()),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginResponse {
+    /// Sent back as `Authorization: Bearer <token>`
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub user: User,
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use super::models::{Role, User};
+
+fn user_from_row(row: &rusqlite::Row) -> Result<User, rusqlite::Error> {
+    Ok(User {
+        id: row.get("id")?,
+        email: row.get("email")?,
+        name: row.get("name")?,
+        password_hash: row.get("password_hash")?,
+        role: row.get("role")?,
+        active: row.get("active")?,
+        created_at: row.get("created_at")?,
+        last_login_at: row.get("last_login_at")?,
+    })
+}
+
+pub fn get_all(conn: &Connection) -> Result<Vec<User>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM users ORDER BY id;")?;
+    stmt.query_map([], user_from_row)
+        .and_then(Iterator::collect)
+}
+
+pub fn get_by_id(conn: &Connection, id: i64) -> Result<User, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM users WHERE id = ?1;")?;
+    stmt.query_row([id], user_from_row)
+}
+
+pub fn get_by_email(conn: &Connection, email: &str) -> Result<User, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT * FROM users WHERE email = ?1;")?;
+    stmt.query_row([email], user_from_row)
+}
+
+pub fn get_active_count_by_role(conn: &Connection, role: Role) -> Result<i64, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT COUNT(*) FROM users WHERE role = ?1 AND active = 1;")?;
+    stmt.query_row([role], |row| row.get(0))
+}
+
+pub fn create(conn: &Connection, user: &mut User) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO users (email, name, password_hash, role, active, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+    )?;
+    stmt.execute(params![
+        user.email,
+        user.name,
+        user.password_hash,
+        user.role,
+        user.active,
+        user.created_at,
+    ])?;
+    user.id = conn.last_insert_rowid();
+    Ok(())
+}
+
+pub fn update(conn: &Connection, user: &User) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "UPDATE users SET name = ?1, password_hash = ?2, role = ?3, active = ?4, last_login_at = ?5
+         WHERE id = ?6;",
+    )?;
+    stmt.execute(params![
+        user.name,
+        user.password_hash,
+        user.role,
+        user.active,
+        user.last_login_at,
+        user.id,
+    ])?;
+    Ok(())
+}
+
+/// Only touches `last_login_at`, so a concurrent update of the user is never reverted.
+pub fn set_last_login(
+    conn: &Connection,
+    id: i64,
+    last_login_at: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE users SET last_login_at = ?1 WHERE id = ?2;",
+        params![last_login_at, id],
+    )?;
+    Ok(())
+}
+
+pub fn create_session(
+    conn: &Connection,
+    token_hash: &str,
+    user_id: i64,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO sessions (token_hash, user_id, created_at, expires_at) VALUES (?1, ?2, ?3, ?4);",
+    )?;
+    stmt.execute(params![token_hash, user_id, created_at, expires_at])?;
+    Ok(())
+}
+
+/// The active user owning an unexpired session.
+pub fn get_by_session(
+    conn: &Connection,
+    token_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<User, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT users.* FROM sessions JOIN users ON users.id = sessions.user_id
+         WHERE sessions.token_hash = ?1 AND sessions.expires_at > ?2 AND users.active = 1;",
+    )?;
+    stmt.query_row(params![token_hash, now], user_from_row)
+}
+
+pub fn delete_session(conn: &Connection, token_hash: &str) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM sessions WHERE token_hash = ?1;", [token_hash])?;
+    Ok(())
+}
+
+pub fn delete_sessions_by_user(conn: &Connection, user_id: i64) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM sessions WHERE user_id = ?1;", [user_id])?;
+    Ok(())
+}
+
+pub fn delete_expired_sessions(
+    conn: &Connection,
+    now: DateTime<Utc>,
+) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM sessions WHERE expires_at <= ?1;", [now])?;
+    Ok(())
+}
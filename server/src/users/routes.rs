@@ -0,0 +1,28 @@
+use super::handlers;
+use super::models::Role;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/auth/login").route(web::post().to(handlers::login)));
+    cfg.service(
+        web::resource("/auth/logout")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Viewer))
+            .route(web::post().to(handlers::logout)),
+    );
+    cfg.service(
+        web::resource("/auth/me")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Viewer))
+            .route(web::get().to(handlers::get_me)),
+    );
+    cfg.service(
+        web::resource("/users")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Admin))
+            .route(web::get().to(handlers::get_users))
+            .route(web::post().to(handlers::post_user)),
+    );
+    cfg.service(
+        web::resource("/users/{id}")
+            .wrap(crate::middlewares::auth::RequireRole::new(Role::Admin))
+            .route(web::patch().to(handlers::patch_user)),
+    );
+}
@@ -0,0 +1,224 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use sha2::{Digest, Sha256};
+
+use super::models::{LoginResponse, Role, User};
+use super::repository;
+use crate::tickets::ServiceError;
+
+const MIN_PASSWORD_LENGTH: usize = 10;
+
+/// Verified when the email is unknown, so that a login takes as long whether the account exists or not
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(generate_token().as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .unwrap_or_default()
+});
+
+fn hash_password(password: &str) -> Result<String, ServiceError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ServiceError::Invalid(format!(
+            "Password must be at least {} characters long",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| ServiceError::Internal(format!("Failed to hash password: {}", e)))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// Tokens are random, so a plain hash is enough to keep them unusable if the database leaks.
+pub(crate) fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub(crate) fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn get_users(conn: &Connection) -> Result<Vec<User>, ServiceError> {
+    repository::get_all(conn).map_err(ServiceError::from)
+}
+
+pub struct CreateUserRequest {
+    pub email: String,
+    pub name: String,
+    pub password: String,
+    pub role: Role,
+}
+
+pub fn create_user(conn: &Connection, req: CreateUserRequest) -> Result<User, ServiceError> {
+    let email = req.email.trim().to_string();
+    if !email.contains('@') {
+        return Err(ServiceError::Invalid(format!("Invalid email: {}", email)));
+    }
+    if req.name.trim().is_empty() {
+        return Err(ServiceError::Invalid("Name must not be empty".to_string()));
+    }
+    match repository::get_by_email(conn, &email) {
+        Ok(_) => {
+            return Err(ServiceError::Conflict(format!(
+                "A user with the email {} already exists",
+                email
+            )))
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut user = User {
+        id: 0,
+        email,
+        name: req.name.trim().to_string(),
+        password_hash: hash_password(&req.password)?,
+        role: req.role,
+        active: true,
+        created_at: chrono::Utc::now(),
+        last_login_at: None,
+    };
+    repository::create(conn, &mut user)?;
+    Ok(user)
+}
+
+pub struct UpdateUserRequest {
+    pub name: Option<String>,
+    pub role: Option<Role>,
+    pub active: Option<bool>,
+    pub password: Option<String>,
+}
+
+pub fn update_user(
+    conn: &Connection,
+    id: i64,
+    req: UpdateUserRequest,
+) -> Result<User, ServiceError> {
+    let mut user = repository::get_by_id(conn, id)?;
+    let was_admin = user.role == Role::Admin && user.active;
+
+    if let Some(name) = req.name {
+        if name.trim().is_empty() {
+            return Err(ServiceError::Invalid("Name must not be empty".to_string()));
+        }
+        user.name = name.trim().to_string();
+    }
+    if let Some(role) = req.role {
+        user.role = role;
+    }
+    if let Some(active) = req.active {
+        user.active = active;
+    }
+    let password_changed = match req.password {
+        Some(password) => {
+            user.password_hash = hash_password(&password)?;
+            true
+        }
+        None => false,
+    };
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    // Somebody must always be able to manage the users
+    if was_admin
+        && !(user.role == Role::Admin && user.active)
+        && repository::get_active_count_by_role(&tx, Role::Admin)? <= 1
+    {
+        return Err(ServiceError::Conflict(
+            "Cannot demote or deactivate the last active admin".to_string(),
+        ));
+    }
+    repository::update(&tx, &user)?;
+    if password_changed || !user.active {
+        repository::delete_sessions_by_user(&tx, user.id)?;
+    }
+    tx.commit()?;
+
+    Ok(user)
+}
+
+pub fn login(
+    conn: &Connection,
+    email: &str,
+    password: &str,
+    ttl: Duration,
+) -> Result<LoginResponse, ServiceError> {
+    let mut user = match repository::get_by_email(conn, email.trim()) {
+        Ok(user) => user,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            verify_password(password, &DUMMY_PASSWORD_HASH);
+            return Err(ServiceError::Unauthorized);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !verify_password(password, &user.password_hash) || !user.active {
+        return Err(ServiceError::Unauthorized);
+    }
+
+    let now = chrono::Utc::now();
+    let expires_at = chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .unwrap_or(now);
+    let token = generate_token();
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    repository::delete_expired_sessions(&tx, now)?;
+    repository::create_session(&tx, &hash_token(&token), user.id, now, expires_at)?;
+    repository::set_last_login(&tx, user.id, now)?;
+    user.last_login_at = Some(now);
+    tx.commit()?;
+
+    Ok(LoginResponse {
+        token,
+        expires_at,
+        user,
+    })
+}
+
+pub fn logout(conn: &Connection, token: &str) -> Result<(), ServiceError> {
+    repository::delete_session(conn, &hash_token(token)).map_err(ServiceError::from)
+}
+
+/// Resolves a session token to its user, `Unauthorized` if it is unknown or expired.
+pub fn authenticate(conn: &Connection, token: &str) -> Result<User, ServiceError> {
+    match repository::get_by_session(conn, &hash_token(token), chrono::Utc::now()) {
+        Ok(user) => Ok(user),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(ServiceError::Unauthorized),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Active staff who act on tickets, told by email when a requester replies.
+pub(crate) fn get_staff_to_notify(conn: &Connection) -> Result<Vec<User>, ServiceError> {
+    Ok(repository::get_all(conn)?
+        .into_iter()
+        .filter(|user| user.active && user.role.allows(Role::Agent))
+        .collect())
+}
+
+pub fn get_user_by_email(conn: &Connection, email: &str) -> Result<User, ServiceError> {
+    repository::get_by_email(conn, email.trim()).map_err(ServiceError::from)
+}
@@ -1,21 +1,122 @@
-pub type Pool = r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>;
-pub type Connection = r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>;
-
-pub fn init_db(conn: &Connection) -> Result<(), rusqlite::Error> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tickets (
-            uuid TEXT PRIMARY KEY,
-            number INTEGER NOT NULL,
-            name TEXT NOT NULL,
-            email TEXT NOT NULL,
-            message TEXT NOT NULL,
-            note TEXT,
-            status TEXT NOT NULL CHECK (status IN ('open', 'closed', 'pending')),
-            created_at TEXT NOT NULL,
-            updated_at TEXT,
-            closed_at TEXT
-        );",
-        [],
-    )?;
+use std::time::Duration;
+
+use actix_web::web;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::config::DatabaseConfig;
+use crate::tickets::ServiceError;
+use crate::utils::migrations::{self, MigrationError};
+
+pub type Pool = r2d2::Pool<SqliteConnectionManager>;
+pub type Connection = r2d2::PooledConnection<SqliteConnectionManager>;
+
+/// Applied to every connection as it is opened.
+fn init_connection(
+    conn: &rusqlite::Connection,
+    busy_timeout: Duration,
+    cache_size: u32,
+) -> Result<(), rusqlite::Error> {
+    // Readers no longer wait for writers. The mode is stored in the database file,
+    // so this only changes anything for the first connection.
+    let mode: String =
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        log::warn!("SQLite refused WAL mode, using {} journal mode", mode);
+    }
+    // Writers wait for each other instead of failing with SQLITE_BUSY. Only when they
+    // take the lock up front: a deferred transaction upgrading from a read fails at once,
+    // so write transactions are IMMEDIATE.
+    conn.busy_timeout(busy_timeout)?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    // Durable in WAL mode except on power loss, and far fewer fsyncs than FULL
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    // Negative values are in KiB rather than pages
+    conn.pragma_update(None, "cache_size", -i64::from(cache_size))
+}
+
+/// Fails at startup when the database cannot be opened rather than on the first request.
+pub fn create_pool(config: &DatabaseConfig) -> Result<Pool, r2d2::Error> {
+    let (busy_timeout, cache_size) = (config.busy_timeout, config.cache_size);
+    let manager = SqliteConnectionManager::file(&config.path)
+        .with_init(move |conn| init_connection(conn, busy_timeout, cache_size));
+    r2d2::Pool::builder()
+        .max_size(config.pool_size)
+        .connection_timeout(config.connect_timeout)
+        .idle_timeout(config.idle_timeout)
+        .build(manager)
+}
+
+/// A connection from the pool, `ServiceError::Unavailable` once every connection stayed
+/// busy for the whole connect timeout.
+pub fn connection(pool: &Pool) -> Result<Connection, ServiceError> {
+    Ok(pool.get()?)
+}
+
+/// Runs `f` on the blocking thread pool with a connection checked out for just as long,
+/// so neither waiting for a connection nor SQLite itself stalls the async workers.
+/// Anything that waits on the network belongs outside of `f`.
+pub async fn run<T, F>(pool: &Pool, f: F) -> Result<T, ServiceError>
+where
+    F: FnOnce(&Connection) -> Result<T, ServiceError> + Send + 'static,
+    T: Send + 'static,
+{
+    let pool = pool.clone();
+    web::block(move || f(&connection(&pool)?))
+        .await
+        .map_err(|e| ServiceError::Internal(format!("Database task failed: {}", e)))?
+}
+
+/// Folds the write-ahead log back into the database so it does not grow unbounded,
+/// and lets SQLite refresh the statistics its query planner relies on.
+fn maintain(conn: &Connection) -> Result<(), ServiceError> {
+    // Busy when a reader still needs part of the log, the next run catches up
+    let (busy, pages, checkpointed): (i64, i64, i64) =
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE);", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    if busy != 0 {
+        log::warn!(
+            "WAL checkpoint incomplete, {} of {} pages copied",
+            checkpointed,
+            pages
+        );
+    }
+    conn.execute_batch("PRAGMA optimize;")?;
     Ok(())
 }
+
+/// Starts the background task that checkpoints and optimizes the database every `interval`.
+pub fn spawn_maintenance(pool: Pool, interval: Duration) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(interval);
+        // The first tick completes immediately, nothing needs doing right after startup
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match run(&pool, maintain).await {
+                Ok(()) => log::debug!("Database maintenance done"),
+                Err(e) => log::error!("Database maintenance failed: {}", e),
+            }
+        }
+    });
+}
+
+pub fn init_db(conn: &Connection) -> Result<(), MigrationError> {
+    let applied = migrations::migrate(conn)?;
+    if !applied.is_empty() {
+        log::info!("Applied {} database migration(s)", applied.len());
+    }
+    Ok(())
+}
+
+/// A fresh migrated in-memory database. A single connection, every connection
+/// to `:memory:` would otherwise open a database of its own.
+#[cfg(test)]
+pub fn test_pool() -> Pool {
+    let pool = r2d2::Pool::builder()
+        .max_size(1)
+        .build(SqliteConnectionManager::memory())
+        .unwrap();
+    init_db(&pool.get().unwrap()).unwrap();
+    pool
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use actix_web::http::header::X_FORWARDED_FOR;
+use actix_web::HttpRequest;
+
+/// Tracked addresses above which idle entries are pruned
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct LockoutSettings {
+    /// Failed attempts from one address before it is locked out
+    pub max_failures: u32,
+    /// How long a lockout lasts, and how long failures are remembered
+    pub duration: Duration,
+    /// Reverse proxies whose `Forwarded` or `X-Forwarded-For` header names the client
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Counts failed logins and token checks per client address and locks out addresses
+/// that keep guessing. Kept in memory, a restart clears every lockout.
+pub struct Lockout {
+    settings: LockoutSettings,
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl Lockout {
+    pub fn new(settings: LockoutSettings) -> Self {
+        Self {
+            settings,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Address of the client, as reported by the proxies when the connection comes from
+    /// a trusted one. Otherwise every client behind a proxy would share its address.
+    pub fn client_ip(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let peer = req.peer_addr()?.ip();
+        let forwarded_for = req
+            .headers()
+            .get_all(X_FORWARDED_FOR)
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+        Some(forwarded_client(
+            peer,
+            &forwarded_for,
+            &self.settings.trusted_proxies,
+        ))
+    }
+
+    /// `Err` with the remaining time while the address is locked out.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let failures = self.failures();
+        match failures.get(&ip).and_then(|f| f.locked_until) {
+            Some(until) if until > now => Err(until - now),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true when this failure locks the address out.
+    pub fn record_failure(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut failures = self.failures();
+        if failures.len() >= PRUNE_THRESHOLD {
+            failures.retain(|_, f| !self.is_stale(f, now));
+        }
+
+        let entry = failures.entry(ip).or_insert(Failures {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        // Start counting afresh once the previous failures are forgotten
+        if self.is_stale(entry, now) {
+            entry.count = 0;
+            entry.locked_until = None;
+        }
+        entry.count += 1;
+        entry.last_failure = now;
+        if entry.count >= self.settings.max_failures && entry.locked_until.is_none() {
+            entry.locked_until = Some(now + self.settings.duration);
+            return true;
+        }
+        false
+    }
+
+    /// Forgets the failures of an address after a successful login.
+    pub fn record_success(&self, ip: IpAddr) {
+        self.failures().remove(&ip);
+    }
+
+    /// A panic while holding the lock cannot leave the counts inconsistent, keep using them.
+    fn failures(&self) -> MutexGuard<'_, HashMap<IpAddr, Failures>> {
+        self.failures.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn is_stale(&self, failures: &Failures, now: Instant) -> bool {
+        failures.locked_until.is_none_or(|until| until <= now)
+            && now.duration_since(failures.last_failure) >= self.settings.duration
+    }
+}
+
+/// Walks `X-Forwarded-For` from the right, the end appended by our own proxies, and
+/// returns the first hop that is not a trusted proxy. Entries to its left were written
+/// by the client and can be anything.
+fn forwarded_client(peer: IpAddr, forwarded_for: &str, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let mut client = peer;
+    for hop in forwarded_for.rsplit(',').map(str::trim) {
+        if !trusted_proxies.contains(&client) {
+            break;
+        }
+        let Some(hop) = hop
+            .parse::<IpAddr>()
+            .ok()
+            .or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        else {
+            // Garbage from a trusted proxy, stop at the last address known to be real
+            break;
+        };
+        client = hop;
+    }
+    client
+}
+
+/// Parses a comma separated list of IP addresses.
+pub fn parse_addresses(value: &str) -> Result<Vec<IpAddr>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(|addr| {
+            addr.parse()
+                .map_err(|_| format!("{} is not an IP address", addr))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lockout(max_failures: u32, duration: Duration) -> Lockout {
+        Lockout::new(LockoutSettings {
+            max_failures,
+            duration,
+            trusted_proxies: Vec::new(),
+        })
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn locks_out_after_max_failures() {
+        let lockout = lockout(3, Duration::from_secs(60));
+        let attacker = ip("203.0.113.7");
+        assert!(!lockout.record_failure(attacker));
+        assert!(!lockout.record_failure(attacker));
+        assert!(lockout.check(attacker).is_ok());
+
+        assert!(lockout.record_failure(attacker));
+        let retry_after = lockout.check(attacker).unwrap_err();
+        assert!(retry_after > Duration::from_secs(55) && retry_after <= Duration::from_secs(60));
+        // Only reported once
+        assert!(!lockout.record_failure(attacker));
+        // Other addresses are not affected
+        assert!(lockout.check(ip("203.0.113.8")).is_ok());
+    }
+
+    #[test]
+    fn success_forgets_failures() {
+        let lockout = lockout(2, Duration::from_secs(60));
+        let client = ip("2001:db8::1");
+        lockout.record_failure(client);
+        lockout.record_success(client);
+        assert!(!lockout.record_failure(client));
+        assert!(lockout.check(client).is_ok());
+    }
+
+    #[test]
+    fn lockout_and_failures_expire() {
+        let lockout = lockout(2, Duration::from_millis(50));
+        let client = ip("203.0.113.7");
+        lockout.record_failure(client);
+        assert!(lockout.record_failure(client));
+        assert!(lockout.check(client).is_err());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(lockout.check(client).is_ok());
+        // Counting starts afresh
+        assert!(!lockout.record_failure(client));
+    }
+
+    #[test]
+    fn client_is_the_first_untrusted_forwarded_hop() {
+        let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+        let direct = ip("198.51.100.1");
+        // Not from a proxy, the header is the client's own words
+        assert_eq!(forwarded_client(direct, "192.0.2.1", &proxies), direct);
+        // Forged entries left of the real client are ignored
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), "192.0.2.66, 203.0.113.7", &proxies),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), "203.0.113.7:4711, 10.0.0.2", &proxies),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), "[2001:db8::7]:4711", &proxies),
+            ip("2001:db8::7")
+        );
+        // Nothing usable, the proxy is the best we know
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), "", &proxies),
+            ip("10.0.0.1")
+        );
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), "unknown", &proxies),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn parses_address_lists() {
+        assert_eq!(
+            parse_addresses(" 10.0.0.1, ,::1 "),
+            Ok(vec![ip("10.0.0.1"), ip("::1")])
+        );
+        assert!(parse_addresses("10.0.0.0/8").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use futures_util::future::BoxFuture;
+use reqwest::{header, Client};
+use serde_json::json;
+
+use super::{Email, MailError, Mailbox, Mailer, SEND_TIMEOUT};
+
+const API_URL: &str = "https://api.brevo.com/v3/smtp/email";
+
+pub struct BrevoMailer {
+    client: Client,
+    api_key: String,
+    sender: Mailbox,
+}
+
+impl BrevoMailer {
+    pub fn new(sender: Mailbox, api_key: String) -> Result<Self, String> {
+        let client = Client::builder()
+            .timeout(SEND_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            client,
+            api_key,
+            sender,
+        })
+    }
+
+    async fn deliver(&self, email: &Email) -> Result<(), MailError> {
+        log::debug!("Sending email to {}", email.to.email);
+        log::debug!("From: {} <{}>", self.sender.name, self.sender.email);
+        log::debug!("Subject: {}", email.subject);
+
+        // Payload according to Brevo's API
+        let mut request_body = json!({
+            "sender": self.sender,
+            "to": [email.to],
+            "subject": email.subject,
+            "htmlContent": email.html
+        });
+        if let Some(text) = &email.text {
+            request_body["textContent"] = json!(text);
+        }
+
+        let response = self
+            .client
+            .post(API_URL)
+            .header("api-key", &self.api_key)
+            .header(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("application/json"),
+            )
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("[brevo] Request failed: {}", e);
+                MailError::Delivery(e.to_string())
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "<failed to read body>".into());
+            log::error!("Error response: {} - {}", status, text);
+            return Err(MailError::Delivery(format!("{} - {}", status, text)));
+        }
+
+        log::info!("Email sent successfully!");
+        Ok(())
+    }
+}
+
+impl Mailer for BrevoMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), MailError>> {
+        Box::pin(self.deliver(email))
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use futures_util::future::BoxFuture;
+use uuid::Uuid;
+
+use super::smtp::build_message;
+use super::{Email, MailError, Mailbox, Mailer};
+
+/// Drops every email as an `.eml` file in a Maildir, readable by most mail clients.
+pub struct MaildirMailer {
+    root: PathBuf,
+    sender: Mailbox,
+}
+
+impl MaildirMailer {
+    pub fn new(sender: Mailbox, root: impl Into<PathBuf>) -> Result<Self, String> {
+        let root = root.into();
+        for dir in ["tmp", "new", "cur"] {
+            std::fs::create_dir_all(root.join(dir))
+                .map_err(|e| format!("Cannot create {}: {}", root.join(dir).display(), e))?;
+        }
+        Ok(Self { root, sender })
+    }
+
+    fn deliver(&self, email: &Email) -> Result<(), MailError> {
+        let message = build_message(&self.sender, email)?;
+        let name = format!("{}.eml", Uuid::new_v4());
+
+        // Write to tmp first so readers never see a partial file in new
+        let tmp = self.root.join("tmp").join(&name);
+        let new = self.root.join("new").join(&name);
+        std::fs::write(&tmp, message.formatted())
+            .and_then(|_| std::fs::rename(&tmp, &new))
+            .map_err(|e| MailError::Delivery(e.to_string()))?;
+
+        log::info!("Email to {} written to {}", email.to.email, new.display());
+        Ok(())
+    }
+}
+
+impl Mailer for MaildirMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), MailError>> {
+        Box::pin(async move { self.deliver(email) })
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::Mutex;
+
+use futures_util::future::BoxFuture;
+
+use super::{Email, MailError, Mailer};
+
+/// Keeps every email in memory instead of sending it, for tests and local runs.
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every email captured so far, oldest first.
+    #[cfg(test)]
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), MailError>> {
+        log::info!(
+            "Captured email to {} with subject {:?}",
+            email.to.email,
+            email.subject
+        );
+        self.sent.lock().unwrap().push(email.clone());
+        Box::pin(async { Ok(()) })
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+
+use crate::config::{MailBackend, MailConfig};
+
+pub mod brevo;
+pub mod file;
+pub mod memory;
+pub mod smtp;
+
+/// Longest a backend may take to hand over one email. The outbox sends one email at
+/// a time, a hung connection would otherwise hold up every email behind it.
+pub const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Mailbox {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: Mailbox,
+    pub subject: String,
+    pub html: String,
+    /// Plain-text alternative of `html`
+    pub text: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    InvalidAddress(String),
+    Delivery(String),
+}
+
+impl std::fmt::Display for MailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MailError::InvalidAddress(e) => write!(f, "Invalid address: {}", e),
+            MailError::Delivery(e) => write!(f, "Delivery failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+/// Outgoing email transport, every backend sends from the configured sender.
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), MailError>>;
+}
+
+/// Builds the configured backend.
+pub fn from_config(config: &MailConfig) -> Result<Arc<dyn Mailer>, String> {
+    let sender = config.sender.clone();
+
+    match &config.backend {
+        MailBackend::Brevo { api_key } => {
+            log::info!("Using the brevo mail backend");
+            Ok(Arc::new(brevo::BrevoMailer::new(
+                sender,
+                api_key.expose().to_string(),
+            )?))
+        }
+        MailBackend::Smtp(settings) => {
+            log::info!("Using the smtp mail backend ({})", settings.host);
+            Ok(Arc::new(smtp::SmtpMailer::new(sender, settings.clone())?))
+        }
+        MailBackend::File { dir } => {
+            log::info!("Using the file mail backend ({})", dir.display());
+            Ok(Arc::new(file::MaildirMailer::new(sender, dir.clone())?))
+        }
+        MailBackend::Memory => {
+            log::info!("Using the memory mail backend");
+            Ok(Arc::new(memory::MemoryMailer::new()))
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use futures_util::future::BoxFuture;
+use lettre::message::{Mailbox as LettreMailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::{Email, MailError, Mailbox, Mailer, SEND_TIMEOUT};
+use crate::config::Secret;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS, usually on port 587
+    StartTls,
+    /// TLS from the first byte, usually on port 465
+    Tls,
+    /// No encryption, only for local relays
+    None,
+}
+
+impl std::str::FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            "none" => Ok(SmtpSecurity::None),
+            other => Err(format!("Unknown SMTP security mode: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    /// Username and password, the server is used without authentication when `None`
+    pub credentials: Option<(String, Secret)>,
+}
+
+/// Builds the RFC 5322 message, shared with the file backend.
+pub fn build_message(sender: &Mailbox, email: &Email) -> Result<Message, MailError> {
+    let parse = |mailbox: &Mailbox| -> Result<LettreMailbox, MailError> {
+        Ok(LettreMailbox::new(
+            Some(mailbox.name.clone()),
+            mailbox
+                .email
+                .parse()
+                .map_err(|e| MailError::InvalidAddress(format!("{}: {}", mailbox.email, e)))?,
+        ))
+    };
+
+    let builder = Message::builder()
+        .from(parse(sender)?)
+        .to(parse(&email.to)?)
+        .subject(&email.subject);
+    let html = SinglePart::html(email.html.clone());
+
+    match &email.text {
+        Some(text) => builder.multipart(
+            MultiPart::alternative()
+                .singlepart(SinglePart::plain(text.clone()))
+                .singlepart(html),
+        ),
+        None => builder.singlepart(html),
+    }
+    .map_err(|e| MailError::Delivery(e.to_string()))
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(sender: Mailbox, settings: SmtpSettings) -> Result<Self, String> {
+        let mut builder = match settings.security {
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)
+                    .map_err(|e| e.to_string())?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)
+                .map_err(|e| e.to_string())?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+        };
+        builder = builder.timeout(Some(SEND_TIMEOUT));
+        if let Some(port) = settings.port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = settings.credentials {
+            builder =
+                builder.credentials(Credentials::new(username, password.expose().to_string()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender,
+        })
+    }
+
+    async fn deliver(&self, email: &Email) -> Result<(), MailError> {
+        let message = build_message(&self.sender, email)?;
+        self.transport.send(message).await.map_err(|e| {
+            log::error!("[smtp] Sending to {} failed: {}", email.to.email, e);
+            MailError::Delivery(e.to_string())
+        })?;
+        log::info!("Email sent successfully!");
+        Ok(())
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), MailError>> {
+        Box::pin(self.deliver(email))
+    }
+}
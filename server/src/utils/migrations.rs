@@ -0,0 +1,416 @@
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
+use sha2::{Digest, Sha256};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        Sha256::digest(self.sql.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Whether a database recorded with `checksum` was migrated by this file or an
+    /// earlier version of it.
+    fn applied_by(&self, checksum: &str) -> bool {
+        self.checksum() == checksum || SUPERSEDED_CHECKSUMS.contains(&(self.version, checksum))
+    }
+}
+
+// Migrations are applied in order and must never be edited once released,
+// the checksum check refuses to start on a database migrated by a different file.
+// A released migration that has to be corrected keeps its previous checksum in
+// `SUPERSEDED_CHECKSUMS`.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_tickets",
+        sql: include_str!("../../migrations/0001_create_tickets.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "add_reopened_count",
+        sql: include_str!("../../migrations/0002_add_reopened_count.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "ticket_numbering",
+        sql: include_str!("../../migrations/0003_ticket_numbering.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "ticket_messages",
+        sql: include_str!("../../migrations/0004_ticket_messages.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "ticket_events",
+        sql: include_str!("../../migrations/0005_ticket_events.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "tickets_fts",
+        sql: include_str!("../../migrations/0006_tickets_fts.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "email_outbox",
+        sql: include_str!("../../migrations/0007_email_outbox.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "users",
+        sql: include_str!("../../migrations/0008_users.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "api_keys",
+        sql: include_str!("../../migrations/0009_api_keys.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "ticket_access_tokens",
+        sql: include_str!("../../migrations/0010_ticket_access_tokens.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "portal",
+        sql: include_str!("../../migrations/0011_portal.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "outbox_retention",
+        sql: include_str!("../../migrations/0012_outbox_retention.sql"),
+    },
+];
+
+/// Checksums of released migrations that were corrected afterwards. Databases migrated
+/// by the earlier file are accepted, the correction only matters to the others.
+const SUPERSEDED_CHECKSUMS: &[(i64, &str)] = &[
+    // 0003 failed on duplicate ticket numbers instead of renumbering them
+    (
+        3,
+        "807797cfda2b724405c48a9b52cbc00ff21aeaf4420d9d3624785d9562a1f403",
+    ),
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Database(rusqlite::Error),
+    ChecksumMismatch { version: i64, name: String },
+    SchemaTooNew { database: i64, supported: i64 },
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(err: rusqlite::Error) -> Self {
+        MigrationError::Database(err)
+    }
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::Database(e) => write!(f, "Database error: {}", e),
+            MigrationError::ChecksumMismatch { version, name } => write!(
+                f,
+                "Migration {:04}_{} was modified after being applied",
+                version, name
+            ),
+            MigrationError::SchemaTooNew {
+                database,
+                supported,
+            } => write!(
+                f,
+                "Database schema version {} is newer than the latest supported version {}",
+                database, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: &'static str,
+    pub applied_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1;")?;
+    stmt.query_row([table], |row| row.get::<_, i64>(0))
+        .map(|count| count > 0)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({});", table))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>("name"))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(names.iter().any(|name| name == column))
+}
+
+fn record(conn: &Connection, migration: &Migration) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO schema_migrations (version, name, checksum, applied_at) VALUES (?1, ?2, ?3, ?4);",
+        params![
+            migration.version,
+            migration.name,
+            migration.checksum(),
+            chrono::Utc::now(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Databases created before migrations existed already contain part of the schema,
+/// so the matching migrations are recorded as applied instead of being replayed.
+fn baseline_legacy_database(conn: &Connection) -> Result<(), rusqlite::Error> {
+    if !table_exists(conn, "tickets")? {
+        return Ok(());
+    }
+
+    log::info!("Existing database without migration history, recording baseline");
+    record(conn, &MIGRATIONS[0])?;
+    if column_exists(conn, "tickets", "reopened_count")? {
+        record(conn, &MIGRATIONS[1])?;
+    }
+    Ok(())
+}
+
+fn ensure_migrations_table(conn: &Connection) -> Result<(), rusqlite::Error> {
+    if table_exists(conn, "schema_migrations")? {
+        return Ok(());
+    }
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    tx.execute(
+        "CREATE TABLE schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );",
+        [],
+    )?;
+    baseline_legacy_database(&tx)?;
+    tx.commit()
+}
+
+pub fn get_applied(conn: &Connection) -> Result<Vec<AppliedMigration>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version;",
+    )?;
+    stmt.query_map([], |row| {
+        Ok(AppliedMigration {
+            version: row.get("version")?,
+            name: row.get("name")?,
+            checksum: row.get("checksum")?,
+            applied_at: row.get("applied_at")?,
+        })
+    })
+    .and_then(Iterator::collect)
+}
+
+fn verify(applied: &[AppliedMigration]) -> Result<(), MigrationError> {
+    for entry in applied {
+        match MIGRATIONS.iter().find(|m| m.version == entry.version) {
+            Some(migration) if migration.applied_by(&entry.checksum) => {}
+            Some(_) => {
+                return Err(MigrationError::ChecksumMismatch {
+                    version: entry.version,
+                    name: entry.name.clone(),
+                })
+            }
+            None => {
+                return Err(MigrationError::SchemaTooNew {
+                    database: entry.version,
+                    supported: latest_version(),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies every pending migration, each one in its own transaction.
+/// Returns the migrations that were applied by this call.
+pub fn migrate(conn: &Connection) -> Result<Vec<&'static Migration>, MigrationError> {
+    ensure_migrations_table(conn)?;
+    let applied = get_applied(conn)?;
+    verify(&applied)?;
+
+    let current = applied.iter().map(|m| m.version).max().unwrap_or(0);
+    let mut newly_applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        log::info!(
+            "Applying migration {:04}_{}",
+            migration.version,
+            migration.name
+        );
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+        tx.execute_batch(migration.sql)?;
+        record(&tx, migration)?;
+        tx.commit()?;
+        newly_applied.push(migration);
+    }
+    Ok(newly_applied)
+}
+
+pub fn status(conn: &Connection) -> Result<Vec<MigrationStatus>, MigrationError> {
+    let applied = if table_exists(conn, "schema_migrations")? {
+        get_applied(conn)?
+    } else {
+        Vec::new()
+    };
+    verify(&applied)?;
+
+    Ok(MIGRATIONS
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            name: migration.name,
+            applied_at: applied
+                .iter()
+                .find(|m| m.version == migration.version)
+                .map(|m| m.applied_at),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrate_to(conn: &Connection, version: i64) {
+        ensure_migrations_table(conn).unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            conn.execute_batch(migration.sql).unwrap();
+            record(conn, migration).unwrap();
+        }
+    }
+
+    fn insert_ticket(conn: &Connection, uuid: &str, number: i64, created_at: &str) {
+        conn.execute(
+            "INSERT INTO tickets (uuid, number, name, email, message, status, created_at)
+             VALUES (?1, ?2, 'Name', 'a@example.org', 'Message', 'open', ?3);",
+            params![uuid, number, created_at],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn applies_every_migration_to_an_empty_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&conn).unwrap().len(), MIGRATIONS.len());
+        assert!(migrate(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn baselines_databases_created_before_migrations() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute_batch(MIGRATIONS[1].sql).unwrap();
+        insert_ticket(&conn, "a", 1, "2024-01-01T00:00:00Z");
+
+        let applied = migrate(&conn).unwrap();
+        // The existing schema is recorded, not replayed
+        assert_eq!(applied.first().map(|m| m.version), Some(3));
+        let versions: Vec<i64> = get_applied(&conn)
+            .unwrap()
+            .iter()
+            .map(|m| m.version)
+            .collect();
+        assert_eq!(versions, (1..=latest_version()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn refuses_schemas_newer_than_supported() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, checksum, applied_at)
+             VALUES (?1, 'future', '', '2030-01-01T00:00:00Z');",
+            [latest_version() + 1],
+        )
+        .unwrap();
+        assert!(matches!(
+            migrate(&conn),
+            Err(MigrationError::SchemaTooNew { .. })
+        ));
+        assert!(status(&conn).is_err());
+    }
+
+    #[test]
+    fn renumbers_duplicate_ticket_numbers() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 2);
+        insert_ticket(&conn, "a", 1, "2024-01-01T00:00:00Z");
+        insert_ticket(&conn, "b", 2, "2024-01-02T00:00:00Z");
+        insert_ticket(&conn, "c", 2, "2024-01-03T00:00:00Z");
+        insert_ticket(&conn, "d", 2, "2024-01-04T00:00:00Z");
+        insert_ticket(&conn, "e", 3, "2024-01-05T00:00:00Z");
+
+        migrate(&conn).unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT uuid, number, reference FROM tickets ORDER BY uuid;")
+            .unwrap();
+        let tickets: Vec<(String, i64, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let numbers: Vec<(&str, i64)> = tickets
+            .iter()
+            .map(|(uuid, number, _)| (uuid.as_str(), *number))
+            .collect();
+        assert_eq!(numbers, [("a", 1), ("b", 2), ("c", 4), ("d", 5), ("e", 3)]);
+        assert!(tickets
+            .iter()
+            .all(|(_, number, reference)| *reference == number.to_string()));
+        let sequence: i64 = conn
+            .query_row("SELECT value FROM ticket_sequence;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sequence, 5);
+    }
+
+    #[test]
+    fn accepts_databases_migrated_by_a_superseded_file() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute(
+            "UPDATE schema_migrations SET checksum = ?1 WHERE version = 3;",
+            [SUPERSEDED_CHECKSUMS[0].1],
+        )
+        .unwrap();
+        assert!(migrate(&conn).is_ok());
+
+        conn.execute(
+            "UPDATE schema_migrations SET checksum = 'edited' WHERE version = 3;",
+            [],
+        )
+        .unwrap();
+        assert!(matches!(
+            migrate(&conn),
+            Err(MigrationError::ChecksumMismatch { version: 3, .. })
+        ));
+    }
+}
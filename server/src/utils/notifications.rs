@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use minijinja::context;
+
+use crate::messages::models::TicketMessage;
+use crate::tickets::models::Ticket;
+use crate::users::models::User;
+use crate::utils::mailer::{Email, Mailbox};
+use crate::utils::public_url::PublicUrl;
+use crate::utils::templates::Templates;
+
+/// Builds the emails sent to requesters about their tickets, and to staff about requester activity.
+pub struct Notifications {
+    templates: Templates,
+    public_url: PublicUrl,
+    /// Lifetime of the ticket links put in the emails
+    link_ttl: Duration,
+}
+
+fn requester(ticket: &Ticket) -> Mailbox {
+    Mailbox {
+        name: ticket.name.clone(),
+        email: ticket.email.clone(),
+    }
+}
+
+impl Notifications {
+    pub fn new(templates: Templates, public_url: PublicUrl, link_ttl: Duration) -> Self {
+        Self {
+            templates,
+            public_url,
+            link_ttl,
+        }
+    }
+
+    pub fn link_ttl(&self) -> Duration {
+        self.link_ttl
+    }
+
+    /// Link to the requester view of a ticket, `token` being one of its access tokens.
+    pub fn ticket_link(&self, token: &str) -> String {
+        self.public_url.ticket_link(token)
+    }
+
+    pub fn ticket_email(&self, ticket: &Ticket, token: &str) -> Result<Email, minijinja::Error> {
+        let (html, text) = self.templates.render_email(
+            "ticket",
+            context! { ticket => ticket, link => self.ticket_link(token) },
+        )?;
+        Ok(Email {
+            to: requester(ticket),
+            subject: "Your ticket has been issued".to_string(),
+            html,
+            text: Some(text),
+        })
+    }
+
+    pub fn notification_email(
+        &self,
+        ticket: &Ticket,
+        token: &str,
+    ) -> Result<Email, minijinja::Error> {
+        let (html, text) = self.templates.render_email(
+            "notification",
+            context! { ticket => ticket, link => self.ticket_link(token) },
+        )?;
+        Ok(Email {
+            to: requester(ticket),
+            subject: "Your ticket has been updated".to_string(),
+            html,
+            text: Some(text),
+        })
+    }
+
+    pub fn reply_email(
+        &self,
+        ticket: &Ticket,
+        message: &TicketMessage,
+        token: &str,
+    ) -> Result<Email, minijinja::Error> {
+        let (html, text) = self.templates.render_email(
+            "reply",
+            context! {
+                ticket => ticket,
+                message => message,
+                link => self.ticket_link(token),
+            },
+        )?;
+        Ok(Email {
+            to: requester(ticket),
+            subject: "You have a new reply on your ticket".to_string(),
+            html,
+            text: Some(text),
+        })
+    }
+
+    /// Tells a staff member that the requester replied, or reopened the ticket when `message` is `None`.
+    pub fn staff_update_email(
+        &self,
+        user: &User,
+        ticket: &Ticket,
+        message: Option<&TicketMessage>,
+    ) -> Result<Email, minijinja::Error> {
+        let (html, text) = self.templates.render_email(
+            "staff_update",
+            context! {
+                user => user,
+                ticket => ticket,
+                message => message,
+                link => self.public_url.join("admin"),
+            },
+        )?;
+        let subject = match message {
+            Some(_) => format!("New reply on ticket {}", ticket.reference),
+            None => format!("Ticket {} was reopened", ticket.reference),
+        };
+        Ok(Email {
+            to: Mailbox {
+                name: user.name.clone(),
+                email: user.email.clone(),
+            },
+            subject,
+            html,
+            text: Some(text),
+        })
+    }
+
+    /// One-time link to the requester portal, `to` being the requester of the latest ticket.
+    pub fn portal_login_email(
+        &self,
+        to: Mailbox,
+        token: &str,
+        ttl: Duration,
+    ) -> Result<Email, minijinja::Error> {
+        let (html, text) = self.templates.render_email(
+            "portal_login",
+            context! {
+                name => to.name,
+                email => to.email,
+                link => self.public_url.join(&format!("portal?login={}", token)),
+                expires_in => ttl.as_secs().div_ceil(60),
+            },
+        )?;
+        Ok(Email {
+            to,
+            subject: "Your tickets".to_string(),
+            html,
+            text: Some(text),
+        })
+    }
+}
@@ -1,9 +1,14 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 pub struct PaginationQuery {
     page: Option<i64>,
     limit: Option<i64>,
+    after: Option<String>,
+    before: Option<String>,
 }
 
 impl PaginationQuery {
@@ -21,7 +26,13 @@ impl PaginationQuery {
                 return Err("Limit must not exceed 100".to_string());
             }
         }
-        Ok(())
+        if self.after.is_some() && self.before.is_some() {
+            return Err("Only one of after and before can be given".to_string());
+        }
+        if self.page.is_some() && (self.after.is_some() || self.before.is_some()) {
+            return Err("Page cannot be combined with a cursor".to_string());
+        }
+        self.cursor().map(|_| ())
     }
 
     pub fn page(&self) -> u32 {
@@ -30,15 +41,70 @@ impl PaginationQuery {
     pub fn limit(&self) -> u32 {
         self.limit.unwrap_or(10).clamp(1, 100) as u32
     }
+
+    /// The cursor to page from, `None` in page mode.
+    pub fn cursor(&self) -> Result<Option<Cursor>, String> {
+        match (&self.after, &self.before) {
+            (Some(token), _) => Ok(Some(Cursor::After(CursorKey::decode(token)?))),
+            (_, Some(token)) => Ok(Some(Cursor::Before(CursorKey::decode(token)?))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Position of an item in a listing ordered by creation date, newest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorKey {
+    pub created_at: DateTime<Utc>,
+    pub uuid: Uuid,
+}
+
+impl CursorKey {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}|{}",
+            self.created_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
+            self.uuid
+        ))
+    }
+
+    pub fn decode(token: &str) -> Result<Self, String> {
+        let invalid = || "Invalid cursor".to_string();
+        let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+        let text = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (created_at, uuid) = text.split_once('|').ok_or_else(invalid)?;
+
+        Ok(Self {
+            created_at: DateTime::parse_from_rfc3339(created_at)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            uuid: Uuid::parse_str(uuid).map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Cursor {
+    /// Items following the key, i.e. older ones
+    After(CursorKey),
+    /// Items preceding the key, i.e. newer ones
+    Before(CursorKey),
 }
 
 #[derive(Debug, serde::Serialize)]
 pub struct PaginatedResponse<T> {
     pub items: Vec<T>,
-    pub page: u32,
+    /// Absent when paging with a cursor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
     pub limit: u32,
     pub total_items: u32,
     pub total_pages: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
 }
 
 impl<T> PaginatedResponse<T> {
@@ -46,10 +112,108 @@ impl<T> PaginatedResponse<T> {
         let total_pages = ((total_items as f64) / (limit as f64)).ceil() as u32;
         Self {
             items,
-            page,
+            page: Some(page),
             limit,
             total_items,
             total_pages,
+            next_cursor: None,
+            prev_cursor: None,
+        }
+    }
+
+    pub fn with_cursors(
+        items: Vec<T>,
+        limit: u32,
+        total_items: u32,
+        next: Option<CursorKey>,
+        prev: Option<CursorKey>,
+    ) -> Self {
+        Self {
+            page: None,
+            ..Self::new(items, 1, limit, total_items)
         }
+        .cursors(next, prev)
+    }
+
+    pub fn cursors(mut self, next: Option<CursorKey>, prev: Option<CursorKey>) -> Self {
+        self.next_cursor = next.map(|key| key.encode());
+        self.prev_cursor = prev.map(|key| key.encode());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(page: Option<i64>, limit: Option<i64>, after: Option<&str>) -> PaginationQuery {
+        PaginationQuery {
+            page,
+            limit,
+            after: after.map(str::to_string),
+            before: None,
+        }
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let key = CursorKey {
+            created_at: DateTime::parse_from_rfc3339("2024-03-09T12:34:56.123456789Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            uuid: Uuid::new_v4(),
+        };
+        let token = key.encode();
+        // Safe in a query string as is
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(CursorKey::decode(&token), Ok(key));
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let no_separator = URL_SAFE_NO_PAD.encode("2024-03-09T12:34:56Z");
+        let bad_uuid = URL_SAFE_NO_PAD.encode("2024-03-09T12:34:56Z|not-a-uuid");
+        let bad_date = URL_SAFE_NO_PAD.encode(format!("yesterday|{}", Uuid::new_v4()));
+        for token in ["", "!!!", &no_separator, &bad_uuid, &bad_date] {
+            assert!(CursorKey::decode(token).is_err(), "{:?}", token);
+        }
+    }
+
+    #[test]
+    fn validates_query() {
+        let cursor = CursorKey {
+            created_at: Utc::now(),
+            uuid: Uuid::new_v4(),
+        }
+        .encode();
+        assert!(query(None, None, None).validate().is_ok());
+        assert!(query(None, Some(100), Some(&cursor)).validate().is_ok());
+        assert!(query(Some(0), None, None).validate().is_err());
+        assert!(query(None, Some(101), None).validate().is_err());
+        assert!(query(Some(2), None, Some(&cursor)).validate().is_err());
+        assert!(query(None, None, Some("garbage")).validate().is_err());
+        let both = PaginationQuery {
+            before: Some(cursor.clone()),
+            ..query(None, None, Some(&cursor))
+        };
+        assert!(both.validate().is_err());
+    }
+
+    #[test]
+    fn counts_pages() {
+        assert_eq!(
+            PaginatedResponse::new(vec![(); 10], 1, 10, 0).total_pages,
+            0
+        );
+        assert_eq!(
+            PaginatedResponse::new(vec![(); 10], 1, 10, 10).total_pages,
+            1
+        );
+        assert_eq!(
+            PaginatedResponse::new(vec![(); 10], 1, 10, 11).total_pages,
+            2
+        );
     }
 }
@@ -0,0 +1,172 @@
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError};
+use serde::Serialize;
+
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// Prefix of the `type` of every problem, followed by its code
+const TYPE_PREFIX: &str = "urn:ticketing:problem:";
+
+tokio::task_local! {
+    /// Identifier of the request being handled, set by the `RequestId` middleware
+    pub static REQUEST_ID: String;
+}
+
+/// The request being handled, if any.
+pub fn request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// An RFC 7807 error body. `code` is stable and meant for clients to match on,
+/// `detail` is for humans and may change.
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    kind: String,
+    title: &'static str,
+    #[serde(serialize_with = "serialize_status")]
+    status: StatusCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// Members specific to the problem, e.g. the invalid fields
+    #[serde(flatten)]
+    extensions: serde_json::Map<String, serde_json::Value>,
+    #[serde(skip)]
+    headers: Vec<(header::HeaderName, String)>,
+}
+
+fn serialize_status<S: serde::Serializer>(status: &StatusCode, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u16(status.as_u16())
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &'static str) -> Self {
+        Self {
+            kind: format!("{}{}", TYPE_PREFIX, code),
+            title: status.canonical_reason().unwrap_or("Error"),
+            status,
+            detail: None,
+            code,
+            request_id: request_id(),
+            extensions: serde_json::Map::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// The generic problem for a status, for responses that carry no explanation.
+    pub fn from_status(status: StatusCode) -> Self {
+        let code = match status {
+            StatusCode::BAD_REQUEST => "bad_request",
+            StatusCode::UNAUTHORIZED => "unauthorized",
+            StatusCode::FORBIDDEN => "forbidden",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+            StatusCode::CONFLICT => "conflict",
+            StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+            StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
+            StatusCode::TOO_MANY_REQUESTS => "too_many_requests",
+            StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+            s if s.is_server_error() => "internal_error",
+            _ => "error",
+        };
+        Self::new(status, code)
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn extension(mut self, name: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(serde_json::Value::Null);
+        self.extensions.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn header(mut self, name: header::HeaderName, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Logs the internal cause of a server error with the request id, so it can be
+    /// found from what the client was told without being told itself.
+    pub fn log_cause(self, cause: impl std::fmt::Display) -> Self {
+        log::error!(
+            "[{}] {}",
+            self.request_id.as_deref().unwrap_or("no request"),
+            cause
+        );
+        self
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        let mut builder = HttpResponseBuilder::new(self.status);
+        builder.content_type(CONTENT_TYPE);
+        for (name, value) in &self.headers {
+            builder.insert_header((name.clone(), value.clone()));
+        }
+        builder.json(self)
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.code,
+            self.detail.as_deref().unwrap_or(self.title)
+        )
+    }
+}
+
+impl ResponseError for Problem {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.response()
+    }
+}
+
+/// Rejected JSON bodies, registered with `web::JsonConfig`.
+pub fn json_error(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let problem = match &err {
+        JsonPayloadError::OverflowKnownLength { .. } | JsonPayloadError::Overflow { .. } => {
+            Problem::from_status(StatusCode::PAYLOAD_TOO_LARGE)
+        }
+        JsonPayloadError::ContentType => Problem::from_status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+            .detail("Expected an application/json body"),
+        JsonPayloadError::Deserialize(e) => {
+            Problem::new(StatusCode::BAD_REQUEST, "invalid_json").detail(e.to_string())
+        }
+        _ => Problem::new(StatusCode::BAD_REQUEST, "invalid_json"),
+    };
+    problem.into()
+}
+
+/// Rejected path segments, such as a malformed UUID, registered with `web::PathConfig`.
+pub fn path_error(err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    let PathError::Deserialize(e) = err else {
+        return Problem::new(StatusCode::BAD_REQUEST, "invalid_path").into();
+    };
+    Problem::new(StatusCode::BAD_REQUEST, "invalid_path")
+        .detail(e.to_string())
+        .into()
+}
+
+/// Rejected query strings, registered with `web::QueryConfig`.
+pub fn query_error(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let QueryPayloadError::Deserialize(e) = err else {
+        return Problem::new(StatusCode::BAD_REQUEST, "invalid_query").into();
+    };
+    Problem::new(StatusCode::BAD_REQUEST, "invalid_query")
+        .detail(e.to_string())
+        .into()
+}
@@ -0,0 +1,63 @@
+use url::Url;
+
+/// Address the frontend is served from, used to build the links sent to requesters
+/// and as the allowed CORS origin.
+#[derive(Clone)]
+pub struct PublicUrl {
+    base: Url,
+}
+
+impl PublicUrl {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid public base URL {}: {}", value, reason);
+        let mut base = Url::parse(value).map_err(|e| invalid(&e.to_string()))?;
+
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(invalid("scheme must be http or https"));
+        }
+        if base.host_str().is_none() {
+            return Err(invalid("missing host"));
+        }
+        if !base.username().is_empty() || base.password().is_some() {
+            return Err(invalid("credentials are not allowed"));
+        }
+        if base.query().is_some() || base.fragment().is_some() {
+            return Err(invalid("query and fragment are not allowed"));
+        }
+
+        // Relative paths are resolved against the last segment, keep the whole path
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        Ok(Self { base })
+    }
+
+    /// Scheme, host and port, as sent by browsers in the `Origin` header.
+    pub fn origin(&self) -> String {
+        self.base.origin().ascii_serialization()
+    }
+
+    /// Absolute URL of `path` relative to the base URL, e.g. `?ticket=...` or `tickets/1`.
+    pub fn join(&self, path: &str) -> String {
+        self.base
+            .join(path)
+            .map(String::from)
+            .unwrap_or_else(|_| self.base.to_string())
+    }
+
+    pub fn ticket_link(&self, token: &str) -> String {
+        self.join(&format!("?ticket={}", token))
+    }
+}
+
+impl std::fmt::Debug for PublicUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.base.as_str())
+    }
+}
+
+impl std::fmt::Display for PublicUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.base)
+    }
+}
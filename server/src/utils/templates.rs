@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use minijinja::{path_loader, Environment, Value};
+
+/// Templates shipped with the binary, any of them can be overridden by a file
+/// with the same name in `TEMPLATE_DIR`.
+const DEFAULT_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "layout.html",
+        include_str!("../../templates/email/layout.html"),
+    ),
+    (
+        "layout.txt",
+        include_str!("../../templates/email/layout.txt"),
+    ),
+    (
+        "ticket.html",
+        include_str!("../../templates/email/ticket.html"),
+    ),
+    (
+        "ticket.txt",
+        include_str!("../../templates/email/ticket.txt"),
+    ),
+    (
+        "notification.html",
+        include_str!("../../templates/email/notification.html"),
+    ),
+    (
+        "notification.txt",
+        include_str!("../../templates/email/notification.txt"),
+    ),
+    (
+        "reply.html",
+        include_str!("../../templates/email/reply.html"),
+    ),
+    ("reply.txt", include_str!("../../templates/email/reply.txt")),
+    (
+        "portal_login.html",
+        include_str!("../../templates/email/portal_login.html"),
+    ),
+    (
+        "portal_login.txt",
+        include_str!("../../templates/email/portal_login.txt"),
+    ),
+    (
+        "staff_update.html",
+        include_str!("../../templates/email/staff_update.html"),
+    ),
+    (
+        "staff_update.txt",
+        include_str!("../../templates/email/staff_update.txt"),
+    ),
+];
+
+/// Email templates rendered with auto-escaping, `.html` templates escape every
+/// interpolated value while `.txt` templates are rendered as is.
+pub struct Templates {
+    env: Environment<'static>,
+}
+
+impl Templates {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        let mut env = Environment::new();
+        env.set_trim_blocks(true);
+
+        let overrides = dir.map(path_loader);
+        env.set_loader(move |name| {
+            if let Some(load) = &overrides {
+                if let Some(source) = load(name)? {
+                    return Ok(Some(source));
+                }
+            }
+            Ok(DEFAULT_TEMPLATES
+                .iter()
+                .find(|(default, _)| *default == name)
+                .map(|(_, source)| source.to_string()))
+        });
+
+        Self { env }
+    }
+
+    /// Uses the overrides found in `dir` if given, and checks that every template compiles.
+    pub fn load(dir: Option<PathBuf>) -> Result<Self, String> {
+        if let Some(dir) = &dir {
+            if !dir.is_dir() {
+                return Err(format!(
+                    "TEMPLATE_DIR is not a directory: {}",
+                    dir.display()
+                ));
+            }
+            log::info!("Loading email templates from {}", dir.display());
+        }
+
+        let templates = Self::new(dir);
+        for (name, _) in DEFAULT_TEMPLATES {
+            templates
+                .env
+                .get_template(name)
+                .map_err(|e| format!("Invalid email template {}: {:#}", name, e))?;
+        }
+        Ok(templates)
+    }
+
+    /// Renders the `{name}.html` body and its `{name}.txt` plain-text alternative.
+    pub fn render_email(
+        &self,
+        name: &str,
+        ctx: Value,
+    ) -> Result<(String, String), minijinja::Error> {
+        let html = self
+            .env
+            .get_template(&format!("{}.html", name))?
+            .render(&ctx)?;
+        let text = self
+            .env
+            .get_template(&format!("{}.txt", name))?
+            .render(&ctx)?;
+        Ok((html, text))
+    }
+}
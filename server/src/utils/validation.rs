@@ -0,0 +1,305 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+use url::Host;
+
+pub const NAME_MAX_LENGTH: usize = 100;
+pub const MESSAGE_MAX_LENGTH: usize = 1000;
+/// RFC 5321 limits on a forward path and its local part
+const EMAIL_MAX_LENGTH: usize = 254;
+const LOCAL_PART_MAX_LENGTH: usize = 64;
+
+/// Top level domains reserved by RFC 2606 and RFC 6761 that never receive mail
+const RESERVED_TLDS: &[&str] = &["example", "invalid", "local", "localhost", "test"];
+
+/// Unquoted dot-atom local part, quoted strings and comments are not accepted
+static LOCAL_PART: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[A-Za-z0-9!#$%&'*+/=?^_`{|}~-]+(\.[A-Za-z0-9!#$%&'*+/=?^_`{|}~-]+)*$").unwrap()
+});
+static DOMAIN_LABEL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?$").unwrap());
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationSettings {
+    /// Domains, and their subdomains, that cannot be used to submit tickets,
+    /// e.g. disposable mailbox providers
+    pub blocked_email_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    /// Stable identifier for clients, one of `required`, `too_long`, `invalid`
+    /// or `blocked_domain`
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Every field error found in a request, at most one per field.
+#[derive(Debug, Clone)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+/// Cleans user input and collects field errors. Each check returns the cleaned value,
+/// which is only meaningful once `finish` succeeded.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+/// NFC normalized, trimmed, with control and bidirectional override characters
+/// removed. Multiline text keeps its newlines and tabs, otherwise every run of
+/// whitespace becomes a single space.
+pub fn clean(value: &str, multiline: bool) -> String {
+    let value: String = value
+        .replace("\r\n", "\n")
+        .nfc()
+        .filter(|c| match c {
+            '\n' | '\t' => true,
+            '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => false,
+            c => !c.is_control(),
+        })
+        .collect();
+    if multiline {
+        value.trim().to_string()
+    } else {
+        value.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn error(&mut self, field: &'static str, code: &'static str, message: String) {
+        self.errors.push(FieldError {
+            field,
+            code,
+            message,
+        });
+    }
+
+    fn length(&mut self, field: &'static str, label: &str, value: &str, max: usize) {
+        let length = value.chars().count();
+        if length == 0 {
+            self.error(field, "required", format!("{} is required", label));
+        } else if length > max {
+            self.error(
+                field,
+                "too_long",
+                format!("{} must be at most {} characters long", label, max),
+            );
+        }
+    }
+
+    /// Single line text such as a name.
+    pub fn text(&mut self, field: &'static str, label: &str, value: &str, max: usize) -> String {
+        let value = clean(value, false);
+        self.length(field, label, &value, max);
+        value
+    }
+
+    /// Free text that keeps its line breaks, such as a message.
+    pub fn multiline(
+        &mut self,
+        field: &'static str,
+        label: &str,
+        value: &str,
+        max: usize,
+    ) -> String {
+        let value = clean(value, true);
+        self.length(field, label, &value, max);
+        value
+    }
+
+    /// Checks the syntax of an address and the rules on its domain, without any DNS
+    /// lookup. The domain is returned lowercased and in its ASCII (punycode) form.
+    pub fn email(
+        &mut self,
+        field: &'static str,
+        value: &str,
+        settings: &ValidationSettings,
+    ) -> String {
+        let value = clean(value, false);
+        if value.is_empty() {
+            self.error(field, "required", "Email is required".to_string());
+            return value;
+        }
+        let invalid = |v: &mut Self| {
+            v.error(field, "invalid", "Email is not a valid address".to_string());
+            value.clone()
+        };
+
+        let Some((local, domain)) = value.rsplit_once('@') else {
+            return invalid(self);
+        };
+        if local.len() > LOCAL_PART_MAX_LENGTH || !LOCAL_PART.is_match(local) {
+            return invalid(self);
+        }
+        // IP literals are rejected along with malformed names
+        let Ok(Host::Domain(domain)) = Host::parse(domain) else {
+            return invalid(self);
+        };
+        let labels: Vec<&str> = domain.split('.').collect();
+        let tld = labels.last().copied().unwrap_or_default();
+        if labels.len() < 2
+            || !labels.iter().all(|label| DOMAIN_LABEL.is_match(label))
+            || tld.chars().all(|c| c.is_ascii_digit())
+        {
+            return invalid(self);
+        }
+
+        let email = format!("{}@{}", local, domain);
+        if email.len() > EMAIL_MAX_LENGTH {
+            self.error(
+                field,
+                "too_long",
+                format!("Email must be at most {} characters long", EMAIL_MAX_LENGTH),
+            );
+        } else if RESERVED_TLDS.contains(&tld) {
+            self.error(
+                field,
+                "invalid",
+                format!("{} cannot receive emails", domain),
+            );
+        } else if settings
+            .blocked_email_domains
+            .iter()
+            .any(|blocked| domain == *blocked || domain.ends_with(&format!(".{}", blocked)))
+        {
+            self.error(
+                field,
+                "blocked_domain",
+                format!("Addresses at {} are not accepted", domain),
+            );
+        }
+        email
+    }
+
+    pub fn finish(self) -> Result<(), ValidationErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(self.errors))
+        }
+    }
+}
+
+/// Parses a comma separated list of domains, lowercased and in their ASCII form.
+pub fn parse_domains(value: &str) -> Result<Vec<String>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|domain| !domain.is_empty())
+        .map(|domain| match Host::parse(domain) {
+            Ok(Host::Domain(domain)) => Ok(domain),
+            _ => Err(format!("{} is not a domain name", domain)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> ValidationSettings {
+        ValidationSettings {
+            blocked_email_domains: vec!["mailinator.com".to_string()],
+        }
+    }
+
+    /// The cleaned address and the error code, if any.
+    fn email(value: &str) -> (String, Option<&'static str>) {
+        let mut validator = Validator::new();
+        let email = validator.email("email", value, &settings());
+        let code = validator.finish().err().map(|errors| errors.0[0].code);
+        (email, code)
+    }
+
+    #[test]
+    fn cleans_text() {
+        assert_eq!(clean("  Jane \t\n Doe  ", false), "Jane Doe");
+        assert_eq!(clean(" line 1\r\n\tline 2 \n", true), "line 1\n\tline 2");
+        // Control characters and bidirectional overrides are dropped
+        assert_eq!(clean("evil\u{202E}txt.exe\u{0007}", false), "eviltxt.exe");
+        // Decomposed characters are composed
+        assert_eq!(clean("Rene\u{0301}", false), "Ren\u{00E9}");
+    }
+
+    #[test]
+    fn checks_lengths() {
+        let mut validator = Validator::new();
+        validator.text("name", "Name", "   ", NAME_MAX_LENGTH);
+        validator.multiline("message", "Message", &"é".repeat(11), 10);
+        validator.multiline("message", "Message", &"é".repeat(10), 10);
+        let codes: Vec<_> = validator
+            .finish()
+            .unwrap_err()
+            .0
+            .iter()
+            .map(|error| (error.field, error.code))
+            .collect();
+        // Length is counted in characters, not bytes
+        assert_eq!(codes, [("name", "required"), ("message", "too_long")]);
+    }
+
+    #[test]
+    fn accepts_valid_addresses() {
+        assert_eq!(
+            email(" Jane.Doe+tickets@Example.ORG "),
+            ("Jane.Doe+tickets@example.org".to_string(), None)
+        );
+        assert_eq!(
+            email("a@bücher.de"),
+            ("a@xn--bcher-kva.de".to_string(), None)
+        );
+        assert_eq!(email("a@sub.domain.co.uk").1, None);
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        assert_eq!(email("").1, Some("required"));
+        for value in [
+            "jane",
+            "jane@",
+            "@example.org",
+            "jane..doe@example.org",
+            ".jane@example.org",
+            "\"jane\"@example.org",
+            "jane@localhost",
+            "jane@[127.0.0.1]",
+            "jane@127.0.0.1",
+            "jane@example.123",
+            "jane@-example.org",
+            "jane@exa_mple.org",
+        ] {
+            assert_eq!(email(value).1, Some("invalid"), "{}", value);
+        }
+        let local = "a".repeat(LOCAL_PART_MAX_LENGTH + 1);
+        assert_eq!(email(&format!("{}@example.org", local)).1, Some("invalid"));
+        let domain = format!("{}.org", vec!["a".repeat(60); 5].join("."));
+        assert_eq!(email(&format!("jane@{}", domain)).1, Some("too_long"));
+    }
+
+    #[test]
+    fn rejects_reserved_and_blocked_domains() {
+        assert_eq!(email("jane@example.test").1, Some("invalid"));
+        assert_eq!(email("jane@mailinator.com").1, Some("blocked_domain"));
+        assert_eq!(email("jane@eu.mailinator.com").1, Some("blocked_domain"));
+        assert_eq!(email("jane@notmailinator.com").1, None);
+    }
+
+    #[test]
+    fn parses_domain_lists() {
+        assert_eq!(
+            parse_domains(" Mailinator.com, ,yopmail.com "),
+            Ok(vec![
+                "mailinator.com".to_string(),
+                "yopmail.com".to_string()
+            ])
+        );
+        assert!(parse_domains("mailinator.com, not a domain").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 1998-2011 The OpenSSL Project.  All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+#ifndef OPENSSL_HEADER_ARM_ARCH_H
+#define OPENSSL_HEADER_ARM_ARCH_H
+
+#include <openssl/target.h>
+
+// arm_arch.h contains symbols used by ARM assembly, and the C code that calls
+// it. It is included as a public header to simplify the build, but is not
+// intended for external use.
+
+#if defined(OPENSSL_ARM) || defined(OPENSSL_AARCH64)
+
+// ARMV7_NEON is true when a NEON unit is present in the current CPU.
+#define ARMV7_NEON (1 << 0)
+
+// ARMV8_AES indicates support for hardware AES instructions.
+#define ARMV8_AES (1 << 2)
+
+// ARMV8_SHA1 indicates support for hardware SHA-1 instructions.
+#define ARMV8_SHA1 (1 << 3)
+
+// ARMV8_SHA256 indicates support for hardware SHA-256 instructions.
+#define ARMV8_SHA256 (1 << 4)
+
+// ARMV8_PMULL indicates support for carryless multiplication.
+#define ARMV8_PMULL (1 << 5)
+
+// ARMV8_SHA512 indicates support for hardware SHA-512 instructions.
+#define ARMV8_SHA512 (1 << 6)
+
+// ARMV8_SHA3 indicates support for hardware SHA-3 instructions including EOR3.
+#define ARMV8_SHA3  (1 << 11)
+
+// Combination of all Armv8 Neon extension bits: 0x087c
+// NOTE: If you add further Armv8 Neon extension bits, adjust
+// "Test algorithm dispatch without CPU indicator or Neon extension capability bits"
+// in util/all_tests.json
+
+// The Neoverse N1, V1, V2, V3, and Apple M1 micro-architectures are detected to
+// allow selecting the fasted implementations for SHA3/SHAKE and AES-GCM.
+// Combination of all CPU indicator bits: 0x47080
+// NOTE: If you add further CPU indicator bits, adjust
+// "Test algorithm dispatch without CPU indicator bits" in util/all_tests.json.
+#define ARMV8_NEOVERSE_N1 (1 << 7)
+#define ARMV8_NEOVERSE_V1 (1 << 12)
+#define ARMV8_APPLE_M (1 << 13)
+#define ARMV8_NEOVERSE_V2 (1 << 14)
+#define ARMV8_NEOVERSE_V3 (1 << 18)
+
+// Combination of CPU indicator bits and Armv8 Neon extension bits: 0x478fc
+
+// ARMV8_DIT indicates support for the Data-Independent Timing (DIT) flag.
+#define ARMV8_DIT (1 << 15)
+// ARMV8_DIT_ALLOWED is a run-time en/disabler for the Data-Independent
+// Timing (DIT) flag capability. It makes the DIT capability allowed when it is
+// first discovered in |OPENSSL_cpuid_setup|. But that bit position in
+// |OPENSSL_armcap_P| can be toggled off and back on at run-time via
+// |armv8_disable_dit| and |armv8_enable_dit|, respectively.
+#define ARMV8_DIT_ALLOWED (1 << 16)
+
+// ARMV8_RNG indicates supports for hardware RNG instruction RNDR.
+#define ARMV8_RNG (1 << 17)
+
+//
+// MIDR_EL1 system register
+//
+// 63___ _ ___32_31___ _ ___24_23_____20_19_____16_15__ _ __4_3_______0
+// |            |             |         |         |          |        |
+// |RES0        | Implementer | Variant | Arch    | PartNum  |Revision|
+// |____ _ _____|_____ _ _____|_________|_______ _|____ _ ___|________|
+//
+
+# define ARM_CPU_IMP_ARM           0x41
+
+# define ARM_CPU_PART_CORTEX_A72   0xD08
+# define ARM_CPU_PART_N1           0xD0C
+# define ARM_CPU_PART_V1           0xD40
+# define ARM_CPU_PART_V2           0xD4F
+# define ARM_CPU_PART_V3           0xD84
+
+# define MIDR_PARTNUM_SHIFT       4
+# define MIDR_PARTNUM_MASK        (0xfffUL << MIDR_PARTNUM_SHIFT)
+# define MIDR_PARTNUM(midr)       \
+           (((midr) & MIDR_PARTNUM_MASK) >> MIDR_PARTNUM_SHIFT)
+
+# define MIDR_IMPLEMENTER_SHIFT   24
+# define MIDR_IMPLEMENTER_MASK    (0xffUL << MIDR_IMPLEMENTER_SHIFT)
+# define MIDR_IMPLEMENTER(midr)   \
+           (((midr) & MIDR_IMPLEMENTER_MASK) >> MIDR_IMPLEMENTER_SHIFT)
+
+# define MIDR_ARCHITECTURE_SHIFT  16
+# define MIDR_ARCHITECTURE_MASK   (0xfUL << MIDR_ARCHITECTURE_SHIFT)
+# define MIDR_ARCHITECTURE(midr)  \
+           (((midr) & MIDR_ARCHITECTURE_MASK) >> MIDR_ARCHITECTURE_SHIFT)
+
+# define MIDR_CPU_MODEL_MASK \
+           (MIDR_IMPLEMENTER_MASK | \
+            MIDR_PARTNUM_MASK     | \
+            MIDR_ARCHITECTURE_MASK)
+
+# define MIDR_CPU_MODEL(imp, partnum) \
+           (((imp)     << MIDR_IMPLEMENTER_SHIFT)  | \
+            (0xfUL       << MIDR_ARCHITECTURE_SHIFT) | \
+            ((partnum) << MIDR_PARTNUM_SHIFT))
+
+# define MIDR_IS_CPU_MODEL(midr, imp, partnum) \
+           (((midr) & MIDR_CPU_MODEL_MASK) == MIDR_CPU_MODEL(imp, partnum))
+
+#endif  // ARM || AARCH64
+
+#endif  // OPENSSL_HEADER_ARM_ARCH_H
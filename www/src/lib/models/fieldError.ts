@@ -0,0 +1,8 @@
+// One invalid field of a rejected request, as returned with a 422
+interface FieldErrorModel {
+	field: string;
+	code: 'required' | 'too_long' | 'invalid' | 'blocked_domain';
+	message: string;
+}
+
+export default FieldErrorModel;
@@ -0,0 +1,26 @@
+interface RequesterMessageModel {
+	kind: 'requester_reply' | 'staff_reply';
+	author_name: string;
+	body: string;
+	created_at: string;
+}
+
+// What the requester sees of their ticket, without the internal fields
+interface RequesterTicketModel {
+	number: number;
+	reference: string;
+	name: string;
+	message: string;
+	status: 'open' | 'pending' | 'closed';
+	created_at: string;
+	updated_at: string | null;
+	closed_at: string | null;
+	messages: RequesterMessageModel[];
+}
+
+interface CreatedTicketModel extends RequesterTicketModel {
+	access_token: string;
+}
+
+export type { RequesterMessageModel, CreatedTicketModel };
+export default RequesterTicketModel;
@@ -1,6 +1,7 @@
 interface TicketModel {
 	uuid: string;
 	number: number;
+	reference: string;
 	name: string;
 	email: string;
 	message: string;
@@ -9,6 +10,8 @@ interface TicketModel {
 	created_at: string;
 	updated_at: string | null;
 	closed_at: string | null;
+	reopened_count: number;
+	time_to_close: number | null;
 }
 
 export default TicketModel;
@@ -1,4 +1,7 @@
 import type TicketModel from '$lib/models/ticket';
+import type RequesterTicketModel from '$lib/models/requesterTicket';
+import type { CreatedTicketModel } from '$lib/models/requesterTicket';
+import type FieldErrorModel from '$lib/models/fieldError';
 import axios from 'axios';
 
 const API_URL: string = '/api';
@@ -13,29 +16,60 @@ async function checkStatus(): Promise<boolean> {
   }
 }
 
-async function getTicket(uuid: string): Promise<TicketModel | null> {
+async function getTicket(accessToken: string): Promise<RequesterTicketModel | null> {
   try {
-    const response = await axios.get(`${API_URL}/tickets/${uuid}`);
-    return response.data as TicketModel;
+    const response = await axios.get(
+      `${API_URL}/requester/tickets/${encodeURIComponent(accessToken)}`
+    );
+    return response.data as RequesterTicketModel;
+  } catch (error) {
+    console.error(error);
+    return null;
+  }
+}
+
+interface LoginResponse {
+  token: string;
+  expires_at: string;
+}
+
+async function login(email: string, password: string): Promise<string | null> {
+  try {
+    const response = await axios.post(`${API_URL}/auth/login`, { email, password });
+    return (response.data as LoginResponse).token;
   } catch (error) {
     console.error(error);
     return null;
   }
 }
 
+async function logout(token: string): Promise<void> {
+  try {
+    await axios.post(`${API_URL}/auth/logout`, null, {
+      headers: {
+        Authorization: `Bearer ${token}`
+      }
+    });
+  } catch (error) {
+    console.error(error);
+  }
+}
+
 interface TicketPage {
   items: TicketModel[];
-  page: number;
+  page?: number;
   limit: number;
   total_items: number;
   total_pages: number;
+  next_cursor?: string;
+  prev_cursor?: string;
 }
 
 async function getTicketPage(token: string, page: number): Promise<TicketPage | null> {
   try {
     const response = await axios.get(`${API_URL}/tickets?page=${page}&limit=10`, {
       headers: {
-        Authorization: token
+        Authorization: `Bearer ${token}`
       }
     });
     return response.data as TicketPage;
@@ -62,7 +96,7 @@ async function updateTicket(
       },
       {
         headers: {
-          Authorization: token
+          Authorization: `Bearer ${token}`
         }
       }
     );
@@ -73,23 +107,154 @@ async function updateTicket(
   }
 }
 
+async function rotateAccessToken(token: string, uuid: string, notify: boolean): Promise<boolean> {
+  try {
+    await axios.post(
+      `${API_URL}/tickets/${uuid}/access-token`,
+      { notify },
+      {
+        headers: {
+          Authorization: `Bearer ${token}`
+        }
+      }
+    );
+    return true;
+  } catch (error) {
+    console.error(error);
+    return false;
+  }
+}
+
+async function replyToTicket(
+  accessToken: string,
+  body: string
+): Promise<RequesterTicketModel | null> {
+  try {
+    const response = await axios.post(
+      `${API_URL}/requester/tickets/${encodeURIComponent(accessToken)}/messages`,
+      { body }
+    );
+    return response.data as RequesterTicketModel;
+  } catch (error) {
+    console.error(error);
+    return null;
+  }
+}
+
+async function reopenTicket(accessToken: string): Promise<RequesterTicketModel | null> {
+  try {
+    const response = await axios.post(
+      `${API_URL}/requester/tickets/${encodeURIComponent(accessToken)}/reopen`
+    );
+    return response.data as RequesterTicketModel;
+  } catch (error) {
+    console.error(error);
+    return null;
+  }
+}
+
+async function requestPortalLink(email: string): Promise<boolean> {
+  try {
+    await axios.post(`${API_URL}/portal/login`, { email });
+    return true;
+  } catch (error) {
+    console.error(error);
+    return false;
+  }
+}
+
+interface PortalSession {
+  token: string;
+  email: string;
+  expires_at: string;
+}
+
+async function openPortalSession(loginToken: string): Promise<PortalSession | null> {
+  try {
+    const response = await axios.post(`${API_URL}/portal/session`, { token: loginToken });
+    return response.data as PortalSession;
+  } catch (error) {
+    console.error(error);
+    return null;
+  }
+}
+
+interface RequesterTicketPage {
+  items: RequesterTicketModel[];
+  page?: number;
+  limit: number;
+  total_items: number;
+  total_pages: number;
+}
+
+async function getPortalTickets(
+  token: string,
+  page: number
+): Promise<RequesterTicketPage | 'expired' | null> {
+  try {
+    const response = await axios.get(`${API_URL}/portal/tickets?page=${page}&limit=10`, {
+      headers: {
+        Authorization: `Bearer ${token}`
+      }
+    });
+    return response.data as RequesterTicketPage;
+  } catch (error) {
+    // Only a rejected token ends the session, other errors may be temporary
+    if (axios.isAxiosError(error) && error.response?.status === 401) {
+      return 'expired';
+    }
+    console.error(error);
+    return null;
+  }
+}
+
+async function portalLogout(token: string): Promise<void> {
+  try {
+    await axios.post(`${API_URL}/portal/logout`, null, {
+      headers: {
+        Authorization: `Bearer ${token}`
+      }
+    });
+  } catch (error) {
+    console.error(error);
+  }
+}
+
 async function issueTicket(
   name: string,
   email: string,
   message: string
-): Promise<TicketModel | null> {
+): Promise<CreatedTicketModel | FieldErrorModel[] | null> {
   try {
     const response = await axios.post(`${API_URL}/tickets`, {
       name,
       email,
       message
     });
-    console.log(response);
-    return response.data as TicketModel;
+    return response.data as CreatedTicketModel;
   } catch (error) {
+    // Rejected fields are reported so the form can show them
+    if (axios.isAxiosError(error) && error.response?.status === 422) {
+      return error.response.data.fields as FieldErrorModel[];
+    }
     console.error(error);
     return null;
   }
 }
 
-export { checkStatus, getTicket, getTicketPage, updateTicket, issueTicket };
+export {
+  checkStatus,
+  login,
+  logout,
+  getTicket,
+  replyToTicket,
+  reopenTicket,
+  getTicketPage,
+  updateTicket,
+  rotateAccessToken,
+  requestPortalLink,
+  openPortalSession,
+  getPortalTickets,
+  portalLogout,
+  issueTicket
+};